@@ -14,4 +14,6 @@ export default {
   createLazyComponent: "m",
   createRemoteFunctionInvoker: "n",
   appendChildrenToElement: "o",
+  cloneTemplate: "p",
+  createStaticElement: "q",
 }
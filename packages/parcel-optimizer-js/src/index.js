@@ -7,6 +7,7 @@
  */
 
 import path from "path";
+import fs from "fs/promises";
 import { fileURLToPath } from "url";
 import nullthrows from "nullthrows";
 import { transform } from "@swc/core";
@@ -14,16 +15,83 @@ import { Optimizer } from "@parcel/plugin";
 import parcelUtils from "@parcel/utils";
 import parcelSourceMap from "@parcel/source-map";
 import ThrowableDiagnostic, { escapeMarkdown } from "@parcel/diagnostic";
+import { hasPipeline, runPipeline } from "./pipeline.js";
 
 const { blobToString, stripAnsi } = parcelUtils;
 const SourceMap = typeof parcelSourceMap === "object" ? parcelSourceMap.default : parcelSourceMap;
 
+/**
+ * Creates a diagnostic pointing at a location of the optimized bundle.
+ * @param {string} message - Message of the diagnostic.
+ * @param {number} line - Line of the location.
+ * @param {number} col - Column of the location.
+ * @param {string} code - Code of the bundle.
+ * @param {import("@parcel/source-map").default | null | undefined} originalMap - Source map of the bundle.
+ * @param {import("@parcel/types").PluginOptions} options - Options of Parcel.
+ * @returns {ThrowableDiagnostic}
+ */
+function createDiagnostic(message, line, col, code, originalMap, options) {
+  const mapping = originalMap?.findClosestMapping(line, col);
+  if (mapping && mapping.original && mapping.source) {
+    const { source, original } = mapping;
+    const filePath = path.resolve(options.projectRoot, source);
+    return new ThrowableDiagnostic({
+      diagnostic: {
+        message,
+        origin: "@parcel/optimizer-swc",
+        codeFrames: [
+          {
+            language: "js",
+            filePath,
+            codeHighlights: [{ start: original, end: original }],
+          },
+        ],
+      },
+    });
+  }
+
+  const loc = {
+    line: line,
+    column: col,
+  };
+
+  return new ThrowableDiagnostic({
+    diagnostic: {
+      message,
+      origin: "@parcel/optimizer-swc",
+      codeFrames: [
+        {
+          language: "js",
+          filePath: undefined,
+          code,
+          codeHighlights: [{ start: loc, end: loc }],
+        },
+      ],
+    },
+  });
+}
+
+/**
+ * Reads the manifests emitted by a previous build, so that props of shared components can be folded.
+ * @param {string} dir - Directory the manifests were written to.
+ * @returns {Promise<object[]>}
+ */
+async function readManifests(dir) {
+  const files = await fs.readdir(dir, { recursive: true }).catch(() => []);
+  return Promise.all(
+    files
+      .filter((file) => file.endsWith(".manifest.json"))
+      .map(async (file) => JSON.parse(await fs.readFile(path.join(dir, file), "utf8"))),
+  );
+}
+
 export default new Optimizer({
   async optimize({
     contents,
     map: originalMap,
     bundle,
     options,
+    logger,
     getSourceMapReference,
   }) {
     if (!bundle.env.shouldOptimize) {
@@ -31,194 +99,195 @@ export default new Optimizer({
     }
 
     const code = await blobToString(contents);
-    let result;
-    try {
-      result = await transform(code, {
-        jsc: {
-          minify: {
-            mangle: {
-              ie8: true,
-              safari10: true,
-            },
-            compress: {
-              passes: 2,
-              keep_fargs: false,
-              negate_iife: false,
-              side_effects: true,
-              properties: false,
-              directives: false,
-              ie8: true,
-            },
-            format: {
-              comments: "all",
-            },
-            safari10: true,
-            toplevel: false,
-            module: false
-          },
+    const prerenderThreshold = parseInt(options.env["npm_package_config_optimizer_prerenderThreshold"] || "", 10);
+    const maxRounds = parseInt(options.env["npm_package_config_optimizer_maxRounds"] || "", 10);
+    const reportDir = options.env["npm_package_config_optimizer_report"];
+    const manifestDir = options.env["npm_package_config_optimizer_manifest"];
+    const manifestInputDir = options.env["npm_package_config_optimizer_manifests"];
+    // Both passes share it, so that manifests left over by another build are ignored.
+    const buildId = options.env["npm_package_config_optimizer_buildId"];
+    if ((manifestDir || manifestInputDir) && !buildId) {
+      throw new ThrowableDiagnostic({
+        diagnostic: {
+          message: "Manifests require `optimizer.buildId` to be set to the same value for both passes.",
+          origin: "@parcel/optimizer-swc",
         },
-        env: {
-          targets: bundle.env.engines.browsers,
-        },
-        minify: true,
-        isModule: false,
-        sourceMaps: !!bundle.env.sourceMap,
-        configFile: false,
-        swcrc: false,
       });
-    } catch (err) {
-      // SWC doesn't give us nice error objects, so we need to parse the message.
-      let message = escapeMarkdown(
-        (
-          stripAnsi(err.message)
-            .split("\n")
-            .find((line) => line.trim().length > 0) || ""
-        )
-          .trim()
-          .replace(/^(×|x)\s+/, ""),
-      );
-      const location = err.message.match(/(?:╭─|,-)\[(\d+):(\d+)\]/);
-      if (location) {
-        const line = Number(location[1]);
-        const col = Number(location[1]);
-        const mapping = originalMap?.findClosestMapping(line, col);
-        if (mapping && mapping.original && mapping.source) {
-          const { source, original } = mapping;
-          const filePath = path.resolve(options.projectRoot, source);
-          throw new ThrowableDiagnostic({
-            diagnostic: {
-              message,
-              origin: "@parcel/optimizer-swc",
-              codeFrames: [
-                {
-                  language: "js",
-                  filePath,
-                  codeHighlights: [{ start: original, end: original }],
-                },
-              ],
-            },
-          });
-        }
-
-        const loc = {
-          line: line,
-          column: col,
-        };
-
-        throw new ThrowableDiagnostic({
-          diagnostic: {
-            message,
-            origin: "@parcel/optimizer-swc",
-            codeFrames: [
-              {
-                language: "js",
-                filePath: undefined,
-                code,
-                codeHighlights: [{ start: loc, end: loc }],
-              },
-            ],
-          },
+    }
+    // Either a path relative to the project root or the JSON dictionary itself.
+    const dictionary = options.env["npm_package_config_optimizer_dictionary"];
+    const isModule = bundle.env.outputFormat === "esmodule";
+    const pluginConfig = {
+      prerenderThreshold: isNaN(prerenderThreshold) ? undefined : prerenderThreshold,
+      maxRounds: isNaN(maxRounds) ? undefined : maxRounds,
+      measureSizes: !!reportDir,
+      explain: options.env["npm_package_config_optimizer_explain"] === "true",
+      lint: options.env["npm_package_config_optimizer_lint"] || undefined,
+      checked: options.env["npm_package_config_optimizer_checked"] === "true",
+      verify: options.env["npm_package_config_optimizer_verify"] === "true",
+      // The graph is written as part of the report.
+      graph: !!reportDir && options.env["npm_package_config_optimizer_graph"] === "true",
+      moduleName: bundle.name,
+      emitManifest: !!manifestDir,
+      buildId,
+      manifests: manifestInputDir ? await readManifests(path.resolve(options.projectRoot, manifestInputDir)) : [],
+      // Passed inline, as the plugin can't read files.
+      dictionary: dictionary
+        ? JSON.parse(
+            dictionary.trimStart().startsWith("{")
+              ? dictionary
+              : await fs.readFile(path.resolve(options.projectRoot, dictionary), "utf8"),
+          )
+        : undefined,
+      reportUnusedTranslations: options.env["npm_package_config_optimizer_reportUnusedTranslations"] === "true",
+    };
+    let result;
+    if (hasPipeline()) {
+      result = await runPipeline(code, {
+        filename: bundle.name,
+        sourceMap: !!bundle.env.sourceMap,
+        module: isModule,
+        safari10: true,
+        targets: bundle.env.engines.browsers,
+        optimizer: pluginConfig,
+      });
+      if (result.error) {
+        const { message, line, column } = result.error;
+        throw createDiagnostic(escapeMarkdown(message), line, column, code, originalMap, options);
+      }
+      if (reportDir) {
+        const reportPath = path.resolve(options.projectRoot, reportDir, `${bundle.name}.report.json`);
+        await fs.mkdir(path.dirname(reportPath), { recursive: true });
+        await fs.writeFile(reportPath, JSON.stringify(result.report, null, 2));
+      }
+      if (manifestDir && result.report.manifest) {
+        const manifestPath = path.resolve(options.projectRoot, manifestDir, `${bundle.name}.manifest.json`);
+        await fs.mkdir(path.dirname(manifestPath), { recursive: true });
+        await fs.writeFile(manifestPath, JSON.stringify(result.report.manifest, null, 2));
+      }
+      for (const { message, line, column, notes = [] } of result.warnings) {
+        const notesText = notes.map((note) => `\n  note (${note.line}:${note.column}): ${note.message}`).join("");
+        logger.warn(createDiagnostic(escapeMarkdown(message + notesText), line, column, code, originalMap, options).diagnostics);
+      }
+    } else {
+      if (reportDir || manifestDir) {
+        logger.warn({
+          message: "Optimization reports and manifests require mango_optimizer_pipeline.wasm, which is missing, so none are written.",
+          origin: "@parcel/optimizer-swc",
         });
       }
-
-      throw err;
-    }
-
-    try {
-      result = await transform(result.code, {
-        jsc: {
-          minify: {
-            mangle: {
-              ie8: true,
+      try {
+        result = await transform(code, {
+          jsc: {
+            minify: {
+              // Mangling reuses names across scopes, which the optimizer can't tell apart.
+              mangle: false,
+              compress: {
+                passes: 2,
+                keep_fargs: false,
+                negate_iife: false,
+                side_effects: true,
+                properties: false,
+                directives: false,
+                ie8: true,
+              },
+              format: {
+                comments: "all",
+              },
               safari10: true,
+              toplevel: false,
+              module: isModule
             },
-            compress: {
-              passes: 2,
-              keep_fargs: false,
-              negate_iife: false,
-              side_effects: true,
-              properties: false,
-              directives: true,
-              ie8: true,
-            },
-            format: {
-              comments: false,
-            },
-            safari10: true,
-            toplevel: false,
-            module: false
           },
-          experimental: {
-            plugins: [
-              [fileURLToPath(import.meta.resolve("./mango_optimizer_js.wasm")), {}]
-            ],
-          }
-        },
-        env: {
-          targets: bundle.env.engines.browsers,
-        },
-        minify: true,
-        isModule: false,
-        sourceMaps: !!bundle.env.sourceMap,
-        configFile: false,
-        swcrc: false,
-      });
-    } catch (err) {
-      // SWC doesn't give us nice error objects, so we need to parse the message.
-      let message = escapeMarkdown(
-        (
-          stripAnsi(err.message)
-            .split("\n")
-            .find((line) => line.trim().length > 0) || ""
-        )
-          .trim()
-          .replace(/^(×|x)\s+/, ""),
-      );
-      const location = err.message.match(/(?:╭─|,-)\[(\d+):(\d+)\]/);
-      if (location) {
-        const line = Number(location[1]);
-        const col = Number(location[1]);
-        const mapping = originalMap?.findClosestMapping(line, col);
-        if (mapping && mapping.original && mapping.source) {
-          const { source, original } = mapping;
-          const filePath = path.resolve(options.projectRoot, source);
-          throw new ThrowableDiagnostic({
-            diagnostic: {
-              message,
-              origin: "@parcel/optimizer-swc",
-              codeFrames: [
-                {
-                  language: "js",
-                  filePath,
-                  codeHighlights: [{ start: original, end: original }],
-                },
-              ],
-            },
-          });
+          env: {
+            targets: bundle.env.engines.browsers,
+          },
+          minify: true,
+          isModule,
+          sourceMaps: !!bundle.env.sourceMap,
+          configFile: false,
+          swcrc: false,
+        });
+      } catch (err) {
+        // SWC doesn't give us nice error objects, so we need to parse the message.
+        let message = escapeMarkdown(
+          (
+            stripAnsi(err.message)
+              .split("\n")
+              .find((line) => line.trim().length > 0) || ""
+          )
+            .trim()
+            .replace(/^(×|x)\s+/, ""),
+        );
+        const location = err.message.match(/(?:╭─|,-)\[(\d+):(\d+)\]/);
+        if (location) {
+          const line = Number(location[1]);
+          const col = Number(location[1]);
+          throw createDiagnostic(message, line, col, code, originalMap, options);
         }
 
-        const loc = {
-          line: line,
-          column: col,
-        };
+        throw err;
+      }
 
-        throw new ThrowableDiagnostic({
-          diagnostic: {
-            message,
-            origin: "@parcel/optimizer-swc",
-            codeFrames: [
-              {
-                language: "js",
-                filePath: undefined,
-                code,
-                codeHighlights: [{ start: loc, end: loc }],
+      try {
+        result = await transform(result.code, {
+          jsc: {
+            minify: {
+              mangle: {
+                ie8: true,
+                safari10: true,
               },
-            ],
+              compress: {
+                passes: 2,
+                keep_fargs: false,
+                negate_iife: false,
+                side_effects: true,
+                properties: false,
+                directives: true,
+                ie8: true,
+              },
+              format: {
+                comments: false,
+              },
+              safari10: true,
+              toplevel: false,
+              module: isModule
+            },
+            experimental: {
+              plugins: [
+                [fileURLToPath(import.meta.resolve("./mango_optimizer_js.wasm")), pluginConfig]
+              ],
+            }
+          },
+          env: {
+            targets: bundle.env.engines.browsers,
           },
+          minify: true,
+          isModule,
+          sourceMaps: !!bundle.env.sourceMap,
+          configFile: false,
+          swcrc: false,
         });
-      }
+      } catch (err) {
+        // SWC doesn't give us nice error objects, so we need to parse the message.
+        let message = escapeMarkdown(
+          (
+            stripAnsi(err.message)
+              .split("\n")
+              .find((line) => line.trim().length > 0) || ""
+          )
+            .trim()
+            .replace(/^(×|x)\s+/, ""),
+        );
+        const location = err.message.match(/(?:╭─|,-)\[(\d+):(\d+)\]/);
+        if (location) {
+          const line = Number(location[1]);
+          const col = Number(location[1]);
+          throw createDiagnostic(message, line, col, code, originalMap, options);
+        }
 
-      throw err;
+        throw err;
+      }
     }
 
     let sourceMap = null;
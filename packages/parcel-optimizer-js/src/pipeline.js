@@ -0,0 +1,55 @@
+/**
+ * Copyright (c) GeeekyBoy
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+import fs from "fs/promises";
+import { existsSync } from "fs";
+import os from "os";
+import path from "path";
+import { WASI } from "wasi";
+import { fileURLToPath } from "url";
+
+const pipelineWasmPath = fileURLToPath(import.meta.resolve("./mango_optimizer_pipeline.wasm"));
+
+/** @type {Promise<WebAssembly.Module> | null} */
+let pipelineModule = null;
+
+/**
+ * Checks whether the WASI build of the optimization pipeline is available.
+ * @returns {boolean}
+ */
+export const hasPipeline = () => existsSync(pipelineWasmPath);
+
+/**
+ * Minifies, optimizes then minifies again a bundle in a single pass.
+ * @param {string} code - Code of the bundle.
+ * @param {object} options - Options of the pipeline.
+ * @returns {Promise<{ code: string, map: string | null, report: object } | { error: { message: string, line: number, column: number } }>}
+ */
+export async function runPipeline(code, options) {
+  pipelineModule ??= fs.readFile(pipelineWasmPath).then((wasm) => WebAssembly.compile(wasm));
+  const workDir = await fs.mkdtemp(path.join(os.tmpdir(), "mango-optimizer-"));
+  try {
+    await fs.writeFile(path.join(workDir, "input.json"), JSON.stringify({ code, options }));
+    const wasi = new WASI({
+      version: "preview1",
+      args: ["mango_optimizer_pipeline", "/work/input.json", "/work/output.json"],
+      preopens: { "/work": workDir },
+      returnOnExit: true,
+    });
+    const instance = await WebAssembly.instantiate(await pipelineModule, {
+      wasi_snapshot_preview1: wasi.wasiImport,
+    });
+    const exitCode = wasi.start(instance);
+    const outputPath = path.join(workDir, "output.json");
+    if (!existsSync(outputPath)) {
+      throw new Error(`Mango optimizer pipeline exited with code ${exitCode}`);
+    }
+    return JSON.parse(await fs.readFile(outputPath, "utf8"));
+  } finally {
+    await fs.rm(workDir, { recursive: true, force: true });
+  }
+}
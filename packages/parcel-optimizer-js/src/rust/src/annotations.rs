@@ -0,0 +1,131 @@
+use swc_common::comments::{Comment, Comments};
+use swc_common::{BytePos, Span, SyntaxContext};
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::Annotation;
+
+const NAMESPACE: &str = "@mango:";
+const NO_OPTIMIZE: &str = "@mango-no-optimize";
+
+// Accepts both `/* EFFECT_DEPS */` and `/* @mango:effect-deps */`, ignoring surrounding whitespace.
+fn parse_annotation(text: &str) -> Annotation {
+  let text = text.trim();
+  let name = match text.strip_prefix(NAMESPACE) {
+    Some(name) => name.trim(),
+    None => text,
+  };
+  match name {
+    "EFFECT_DEPS" | "effect-deps" => Annotation::EffectDeps,
+    "IMMEDIATE_EFFECT_DEPS" | "immediate-effect-deps" => Annotation::ImmediateEffectDeps,
+    "STATE_DEPS" | "state-deps" => Annotation::StateDeps,
+    "DYNAMIC_ATTRS" | "dynamic-attrs" => Annotation::DynamicAttrs,
+    "DYNAMIC_VIEW_DEPS" | "dynamic-view-deps" => Annotation::DynamicViewDeps,
+    _ => Annotation::None,
+  }
+}
+
+/// Scans all comments leading the node, then those trailing whatever ends right where it starts.
+pub(crate) fn get_annotation<C>(comments: &C, span: Span) -> Annotation
+where
+  C: Comments,
+{
+  let leading = comments.get_leading(span.lo).unwrap_or_default();
+  let trailing = comments.get_trailing(span.lo).unwrap_or_default();
+  leading
+    .iter()
+    .chain(&trailing)
+    .map(|comment| parse_annotation(&comment.text))
+    .find(|annotation| *annotation != Annotation::None)
+    .unwrap_or_default()
+}
+
+/// Whether the node is preceded by a `/* @mango-no-optimize */` pragma.
+pub(crate) fn is_opted_out(comments: &dyn Comments, span: Span) -> bool {
+  comments
+    .get_leading(span.lo)
+    .unwrap_or_default()
+    .iter()
+    .any(|comment| comment.text.trim() == NO_OPTIMIZE)
+}
+
+/// Stands for the values passed by calls excluded with `@mango-no-optimize`, which never count as constant.
+pub(crate) fn opted_out_dep() -> Id {
+  (NO_OPTIMIZE.into(), SyntaxContext::empty())
+}
+
+/// Removes recognized annotation comments so they don't end up in the output.
+pub(crate) struct AnnotationStripper<'a, C>
+where
+  C: Comments,
+{
+  comments: &'a C,
+}
+
+impl<'a, C> AnnotationStripper<'a, C>
+where
+  C: Comments,
+{
+  pub(crate) fn new(comments: &'a C) -> Self {
+    Self { comments }
+  }
+  fn strip(&self, pos: BytePos) {
+    let is_kept = |comment: &Comment| parse_annotation(&comment.text) == Annotation::None;
+    if let Some(leading) = self.comments.take_leading(pos) {
+      self
+        .comments
+        .add_leading_comments(pos, leading.into_iter().filter(is_kept).collect());
+    }
+    if let Some(trailing) = self.comments.take_trailing(pos) {
+      self
+        .comments
+        .add_trailing_comments(pos, trailing.into_iter().filter(is_kept).collect());
+    }
+  }
+}
+
+impl<C> Visit for AnnotationStripper<'_, C>
+where
+  C: Comments,
+{
+  fn visit_array_lit(&mut self, n: &ArrayLit) {
+    if get_annotation(self.comments, n.span) != Annotation::None {
+      self.strip(n.span.lo);
+    }
+    n.visit_children_with(self);
+  }
+}
+
+#[test]
+fn test_annotations() {
+  assert_eq!(parse_annotation(" EFFECT_DEPS "), Annotation::EffectDeps);
+  assert_eq!(parse_annotation("STATE_DEPS"), Annotation::StateDeps);
+  assert_eq!(parse_annotation("@mango:dynamic-attrs"), Annotation::DynamicAttrs);
+  assert_eq!(
+    parse_annotation("\n @mango: immediate-effect-deps "),
+    Annotation::ImmediateEffectDeps
+  );
+  assert_eq!(parse_annotation("#__PURE__"), Annotation::None);
+  assert_eq!(parse_annotation("@mango:EFFECT-DEPS"), Annotation::None);
+
+  let output = crate::run_optimizer(
+    r#"
+(function () {
+  var m = mango;
+  var Badge = function (props) {
+    "use mango_component";
+    "use 2";
+    var text = props.text;
+    var tone = props.tone;
+    m.f(function () { console.log(m.b(text), m.b(tone)); }, /*@mango:effect-deps*/[text, tone]);
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /*#__PURE__*/ /*DYNAMIC_VIEW_DEPS*/[text])]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* @mango:dynamic-attrs */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Badge({ text: m.a("new"), tone: count })]);
+})();
+"#,
+  );
+  assert!(output.contains("m.f(function(){console.log(text,m.b(tone));},[tone]);"));
+  assert!(output.contains(r#"m.j("span",void 0,[text])"#));
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use swc_atoms::Atom;
+use swc_common::comments::Comments;
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::propagation::propagate_constants;
+use crate::{MainVisitor, Options, Report, Round1Visitor, RuntimeAliasCollector, StateUsageCollector};
+
+#[derive(Default, Debug, Clone)]
+pub struct ComponentGraph {
+  pub components: HashMap<Id, ComponentInfo>,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ComponentInfo {
+  pub props: HashMap<Atom, PropInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropInfo {
+  pub local_name: Id,
+  /// Props and states the values passed to this prop depend on.
+  pub deps: HashSet<Id>,
+  pub is_used: bool,
+  pub is_default: bool,
+  /// Set when the component is referenced outside of calls, in which case none of its props are optimized.
+  pub is_escaped: bool,
+  /// Set when every call site passes a value that never changes, so `optimize` folds the prop.
+  pub is_constant: bool,
+}
+
+/// Collects the components of a resolved program along with their props and how they are used by call sites.
+pub fn analyze<C>(program: &Program, comments: &C) -> ComponentGraph
+where
+  C: Comments,
+{
+  let mut components = Default::default();
+  program.visit_children_with(&mut Round1Visitor {
+    comments: Some(comments),
+    external_usages: &Default::default(),
+    components: &mut components,
+    bailouts: &mut Default::default(),
+    discover_components: true,
+  });
+  let mut runtime_aliases = RuntimeAliasCollector {
+    comments,
+    aliases: Default::default(),
+  };
+  program.visit_with(&mut runtime_aliases);
+  let mut state_usages = StateUsageCollector::new(comments, &runtime_aliases.aliases);
+  program.visit_with(&mut state_usages);
+  // Propagation prunes the deps it resolved, which are reported as collected.
+  let (const_props_usages, _) = propagate_constants(&mut components.clone(), state_usages.stable_candidates());
+  ComponentGraph {
+    components: components
+      .into_iter()
+      .map(|(id, props)| {
+        let props = props
+          .into_iter()
+          .map(|((name, _), prop)| {
+            let info = PropInfo {
+              is_constant: const_props_usages.contains_key(&prop.local_name),
+              local_name: prop.local_name,
+              deps: prop.deps,
+              is_used: prop.is_used,
+              is_default: prop.is_default,
+              is_escaped: prop.is_escaped,
+            };
+            (name, info)
+          })
+          .collect();
+        (id, ComponentInfo { props })
+      })
+      .collect(),
+  }
+}
+
+/// Optimizes a program that went through `resolver`. Must be called within `GLOBALS`.
+pub fn optimize<C>(program: &mut Program, comments: &C, options: &Options) -> Report
+where
+  C: Comments + Clone,
+{
+  let mut visitor = MainVisitor {
+    comments: comments.clone(),
+    components: Default::default(),
+    options: options.clone(),
+    report: Default::default(),
+  };
+  visitor.visit_mut_program(program);
+  visitor.report
+}
+
+#[test]
+fn test_analyze() {
+  use swc_common::comments::SingleThreadedComments;
+  use swc_common::{FileName, Mark, SourceMap, GLOBALS};
+  use swc_ecma_parser::{parse_file_as_script, Syntax};
+  use swc_ecma_transforms::resolver;
+
+  let cm: std::sync::Arc<SourceMap> = Default::default();
+  let fm = cm.new_source_file(
+    FileName::Anon.into(),
+    r#"
+(function () {
+  var m = mango;
+  var Button = function (props) {
+    "use mango_component";
+    "use 4";
+    var label = props.label;
+    var size = props.size || "md";
+    var _u = props.size !== undefined;
+    var icon = props.icon;
+    return m.j("button", void 0, [label]);
+  };
+  var count = m.a(0);
+  var step = m.a(1);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(step, m.b(step) + 1); }; }]]);
+  m.o(document.body, [
+    Button({ label: m.a(function () { return m.b(count) + ""; }, /* STATE_DEPS */[count]), size: m.a("lg") }),
+    Button({ label: "+", size: m.a(function () { return m.b(step) + ""; }, /* STATE_DEPS */[step]) })
+  ]);
+})();
+"#
+    .to_string(),
+  );
+  let comments = SingleThreadedComments::default();
+  let script = parse_file_as_script(
+    &fm,
+    Syntax::Es(Default::default()),
+    EsVersion::latest(),
+    Some(&comments),
+    &mut vec![],
+  )
+  .expect("failed to parse a js file as a script");
+  GLOBALS.set(&Default::default(), || {
+    let mut program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+    let graph = analyze(&program, &comments);
+    assert_eq!(graph.components.len(), 1);
+    let (id, button) = graph.components.iter().next().unwrap();
+    assert_eq!(&*id.0, "Button");
+    assert_eq!(button.props.len(), 3);
+    let prop = |name: &str| &button.props[&Atom::from(name)];
+    assert!(prop("label").is_used && !prop("label").is_default);
+    assert_eq!(
+      prop("label").deps.iter().map(|dep| &*dep.0).collect::<Vec<_>>(),
+      vec!["count"]
+    );
+    assert!(prop("label").is_constant);
+    assert!(prop("size").is_used && prop("size").is_default && !prop("size").is_constant);
+    assert!(!prop("icon").is_used && prop("icon").is_constant);
+
+    let report = optimize(&mut program, &comments, &Default::default());
+    assert_eq!(report.rounds, 1);
+  });
+}
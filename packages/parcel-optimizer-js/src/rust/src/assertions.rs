@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+use swc_atoms::Atom;
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::runtime::{runtime_callee, CREATE_EFFECT};
+use crate::ComponentProp;
+
+/// Instead of unwrapping props assumed constant, logs an error whenever their state is set at runtime.
+pub(crate) struct AssertionInjector<'a> {
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  const_props: &'a HashSet<Id>,
+  aliases: &'a HashSet<Id>,
+}
+
+impl<'a> AssertionInjector<'a> {
+  pub(crate) fn new(
+    components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+    const_props: &'a HashSet<Id>,
+    aliases: &'a HashSet<Id>,
+  ) -> Self {
+    Self {
+      components,
+      const_props,
+      aliases,
+    }
+  }
+}
+
+impl AssertionInjector<'_> {
+  fn inject(&self, component: &Ident, function: &mut Function) {
+    let Some(props) = self.components.get(&component.to_id()) else {
+      return;
+    };
+    let props_param = match function.params.first() {
+      Some(Param {
+        pat: Pat::Ident(props_param),
+        ..
+      }) => Some(props_param.to_id()),
+      _ => None,
+    };
+    let Some(body) = &mut function.body else {
+      return;
+    };
+    let mut finder = AliasFinder {
+      aliases: self.aliases,
+      alias: None,
+    };
+    body.visit_with(&mut finder);
+    let Some(alias) = finder.alias else {
+      return;
+    };
+    let mut const_props: Vec<(&Atom, &Id)> = props
+      .iter()
+      .filter(|(_, prop)| prop.is_used && self.const_props.contains(&prop.local_name))
+      .map(|((name, _), prop)| (name, &prop.local_name))
+      .collect();
+    const_props.sort();
+    let preamble_end = body
+      .stmts
+      .iter()
+      .skip(2)
+      .take_while(|stmt| is_preamble_decl(stmt, props_param.as_ref()))
+      .count()
+      + 2;
+    let assertions = const_props
+      .into_iter()
+      .map(|(name, local_name)| assertion(&alias, &component.sym, name, local_name));
+    body.stmts.splice(preamble_end..preamble_end, assertions);
+  }
+}
+
+impl VisitMut for AssertionInjector<'_> {
+  fn visit_mut_var_declarator(&mut self, n: &mut VarDeclarator) {
+    n.visit_mut_children_with(self);
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&n.name, &mut n.init) {
+      if let Expr::Fn(FnExpr { function, .. }) = &mut **init {
+        self.inject(id, function);
+      }
+    }
+  }
+  fn visit_mut_fn_decl(&mut self, n: &mut FnDecl) {
+    n.visit_mut_children_with(self);
+    self.inject(&n.ident, &mut n.function);
+  }
+  fn visit_mut_default_decl(&mut self, n: &mut DefaultDecl) {
+    n.visit_mut_children_with(self);
+    if let DefaultDecl::Fn(FnExpr {
+      ident: Some(ident),
+      function,
+    }) = n
+    {
+      self.inject(ident, function);
+    }
+  }
+}
+
+struct AliasFinder<'a> {
+  aliases: &'a HashSet<Id>,
+  alias: Option<Ident>,
+}
+
+impl Visit for AliasFinder<'_> {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((alias, _)) = runtime_callee(n).filter(|(alias, _)| self.aliases.contains(&alias.to_id())) {
+      self.alias.get_or_insert_with(|| alias.clone());
+    }
+    n.visit_children_with(self);
+  }
+}
+
+fn is_preamble_decl(stmt: &Stmt, props_param: Option<&Id>) -> bool {
+  let is_prop_access = |expr: &Expr| match expr {
+    Expr::Member(MemberExpr { obj, .. }) => match (&**obj, props_param) {
+      (Expr::Ident(obj), Some(props_param)) => obj.to_id() == *props_param,
+      (Expr::Ident(obj), None) => &*obj.sym == "props",
+      _ => false,
+    },
+    _ => false,
+  };
+  let Stmt::Decl(Decl::Var(var_decl)) = stmt else {
+    return false;
+  };
+  var_decl.decls.iter().all(|decl| match decl.init.as_deref() {
+    Some(Expr::Bin(BinExpr { left, .. })) => is_prop_access(left),
+    Some(init) => is_prop_access(init),
+    None => false,
+  })
+}
+
+// `local instanceof Array && alias.f(function () { console.error(new Error(...)); }, [local]);`
+fn assertion(alias: &Ident, component: &Atom, name: &Atom, local_name: &Id) -> Stmt {
+  let local = || Box::new(Expr::Ident(local_name.clone().into()));
+  let message = format!(
+    "mango: prop `{}` of `{}` was assumed constant, but its state was set",
+    name, component
+  );
+  let error = NewExpr {
+    span: DUMMY_SP,
+    callee: Box::new(Expr::Ident(quote_ident!("Error").into())),
+    args: Some(vec![Lit::Str(message.into()).as_arg()]),
+    ..Default::default()
+  };
+  let log = member_expr!(Default::default(), DUMMY_SP, console.error).as_call(DUMMY_SP, vec![error.as_arg()]);
+  let effect = Function {
+    body: Some(BlockStmt {
+      stmts: vec![log.into_stmt()],
+      ..Default::default()
+    }),
+    ..Default::default()
+  };
+  let deps = ArrayLit {
+    span: DUMMY_SP,
+    elems: vec![Some(local().as_arg())],
+  };
+  let create_effect = alias
+    .clone()
+    .make_member(quote_ident!(CREATE_EFFECT))
+    .as_call(DUMMY_SP, vec![effect.as_arg(), deps.as_arg()]);
+  let is_state = BinExpr {
+    span: DUMMY_SP,
+    op: op!("instanceof"),
+    left: local(),
+    right: Box::new(Expr::Ident(quote_ident!("Array").into())),
+  };
+  BinExpr {
+    span: DUMMY_SP,
+    op: op!("&&"),
+    left: Box::new(is_state.into()),
+    right: Box::new(create_effect),
+  }
+  .into_stmt()
+}
+
+#[test]
+fn test_assertions() {
+  let output = crate::run_optimizer_with_config(
+    r#"
+(function () {
+  var m = mango;
+  var Card = function (props) {
+    "use mango_component";
+    "use 3";
+    var title = props.title;
+    var tone = props.tone || m.a("grey");
+    var _u = props.tone !== undefined;
+    return m.j("div", void 0, [m.i(function () { return m.b(title); }, /* DYNAMIC_VIEW_DEPS */[title]), tone]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Card({ title: m.a("Hi"), tone: count }), Card({ title: m.a("Bye") })]);
+})();
+"#,
+    crate::Options {
+      checked: true,
+      ..Default::default()
+    },
+  );
+  assert!(output.contains(
+    r#"var _u=props.tone!==undefined;title instanceof Array&&m.f(function(){console.error(new Error("mango: prop `title` of `Card` was assumed constant, but its state was set"));},[title]);return"#
+  ));
+  assert!(!output.contains("prop `tone`"));
+  assert!(output.contains(r#"m.i(function(){return m.b(title);},[title])"#));
+  assert!(output.contains(r#"Card({title:m.a("Hi"),tone:count})"#));
+}
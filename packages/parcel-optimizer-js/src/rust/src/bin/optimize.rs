@@ -0,0 +1,197 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::{env, fs, io, process};
+
+use mango_optimizer_js::{run_pipeline, Dictionary, LintLevel, PipelineOptions, Targets};
+
+const USAGE: &str = "Usage: mango-optimize [options] [input.js]
+
+Reads the bundle from `input.js`, or stdin when omitted or `-`.
+
+Options:
+  -o, --output <file>            Write the optimized bundle to a file instead of stdout
+      --input-source-map <file>  Source map of the input bundle
+      --source-map <file>        Write the updated source map
+      --report <file>            Write a JSON report of the optimizations
+      --graph <file>             Write the dependency graph of props and states, as DOT for `.dot` files, JSON otherwise
+      --emit-manifest <file>     Write a JSON manifest of how the components the bundle exports or imports are used
+      --manifest <file>          Fold props of shared components using a manifest of another bundle (repeatable)
+      --module-name <name>       Name of the bundle in manifests (default: the input path)
+      --build-id <id>            Identify the build in emitted manifests, ignoring manifests of other builds
+      --dictionary <file>        Inline translations from a JSON locale dictionary
+      --report-unused-translations
+                                 Warn about ids of the dictionary the bundle never uses
+      --extract-translations <file>
+                                 Write a JSON catalog of the translation calls, leaving them in place
+      --passes <n>               Number of minifier compress passes (default: 2)
+      --max-rounds <n>           Maximum number of optimization rounds
+      --prerender-threshold <n>  Prerender static subtrees with more than <n> elements
+      --targets <query>          Transpile the bundle for the browsers of a browserslist query
+      --no-ie8                   Do not keep the output compatible with IE8
+      --no-safari10              Do not work around Safari 10 bugs when mangling
+      --module                   Parse the bundle as an ES module
+      --checked                  Keep props assumed constant as states and log an error if they change at runtime
+      --verify                   Check the optimized bundle for structural invariants and fail on violations
+      --explain                  Warn about props that could not be folded and why
+      --lint                     Warn about undeclared props passed to components and props never passed
+      --strict                   Like `--lint`, but fail on lint violations
+  -h, --help                     Print this help";
+
+#[derive(Default)]
+struct Args {
+  input: Option<String>,
+  output: Option<String>,
+  input_source_map: Option<String>,
+  source_map: Option<String>,
+  report: Option<String>,
+  graph: Option<String>,
+  emit_manifest: Option<String>,
+  manifests: Vec<String>,
+  translations: Option<String>,
+  options: PipelineOptions,
+}
+
+fn fail(message: impl AsRef<str>, status: i32) -> ! {
+  eprintln!("mango-optimize: {}", message.as_ref());
+  process::exit(status);
+}
+
+fn parse_args() -> Args {
+  let mut args = Args::default();
+  let mut argv = env::args().skip(1);
+  while let Some(arg) = argv.next() {
+    let mut value = |name: &str| {
+      argv
+        .next()
+        .unwrap_or_else(|| fail(format!("missing value for `{}`\n\n{}", name, USAGE), 2))
+    };
+    let number = |name: &str, value: String| {
+      value
+        .parse::<usize>()
+        .unwrap_or_else(|_| fail(format!("`{}` expects a number, got `{}`", name, value), 2))
+    };
+    match arg.as_str() {
+      "-o" | "--output" => args.output = Some(value(&arg)),
+      "--input-source-map" => args.input_source_map = Some(value(&arg)),
+      "--source-map" => args.source_map = Some(value(&arg)),
+      "--report" => args.report = Some(value(&arg)),
+      "--graph" => args.graph = Some(value(&arg)),
+      "--emit-manifest" => args.emit_manifest = Some(value(&arg)),
+      "--manifest" => args.manifests.push(value(&arg)),
+      "--module-name" => args.options.optimizer.module_name = Some(value(&arg)),
+      "--build-id" => args.options.optimizer.build_id = Some(value(&arg)),
+      "--report-unused-translations" => args.options.optimizer.report_unused_translations = true,
+      "--extract-translations" => args.translations = Some(value(&arg)),
+      "--dictionary" => args.options.optimizer.dictionary = Some(Dictionary::Path(value(&arg))),
+      "--passes" => args.options.passes = number(&arg, value(&arg)),
+      "--max-rounds" => args.options.optimizer.max_rounds = Some(number(&arg, value(&arg))),
+      "--prerender-threshold" => args.options.optimizer.prerender_threshold = Some(number(&arg, value(&arg))),
+      "--targets" => args.options.targets = Some(Targets::Query(value(&arg).into())),
+      "--no-ie8" => args.options.ie8 = false,
+      "--no-safari10" => args.options.safari10 = false,
+      "--module" => args.options.module = true,
+      "--checked" => args.options.optimizer.checked = true,
+      "--verify" => args.options.optimizer.verify = true,
+      "--explain" => args.options.optimizer.explain = true,
+      "--lint" => args.options.optimizer.lint = Some(LintLevel::Warn),
+      "--strict" => args.options.optimizer.lint = Some(LintLevel::Error),
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        process::exit(0);
+      }
+      "-" => args.input = None,
+      _ if arg.starts_with('-') => fail(format!("unknown option `{}`\n\n{}", arg, USAGE), 2),
+      _ if args.input.is_some() => fail(format!("unexpected argument `{}`\n\n{}", arg, USAGE), 2),
+      _ => args.input = Some(arg),
+    }
+  }
+  args
+}
+
+fn read(path: &str) -> String {
+  fs::read_to_string(path).unwrap_or_else(|err| fail(format!("failed to read `{}`: {}", path, err), 2))
+}
+
+fn write(path: &str, contents: &str) {
+  fs::write(path, contents).unwrap_or_else(|err| fail(format!("failed to write `{}`: {}", path, err), 2));
+}
+
+fn main() {
+  let mut args = parse_args();
+  let code = match &args.input {
+    Some(path) => read(path),
+    None => {
+      let mut code = String::new();
+      io::stdin()
+        .read_to_string(&mut code)
+        .unwrap_or_else(|err| fail(format!("failed to read stdin: {}", err), 2));
+      code
+    }
+  };
+  args.options.filename = args.input.clone();
+  args.options.input_source_map = args.input_source_map.as_deref().map(read);
+  args.options.source_map = args.source_map.is_some();
+  args.options.optimizer.measure_sizes = args.report.is_some();
+  args.options.optimizer.graph = args.graph.is_some();
+  args.options.optimizer.emit_manifest = args.emit_manifest.is_some();
+  args.options.extract_translations = args.translations.is_some();
+  args.options.optimizer.manifests = args
+    .manifests
+    .iter()
+    .map(|path| {
+      serde_json::from_str(&read(path)).unwrap_or_else(|err| fail(format!("invalid manifest `{}`: {}", path, err), 2))
+    })
+    .collect();
+  if args.options.optimizer.module_name.is_none() {
+    args.options.optimizer.module_name = args.input.clone();
+  }
+
+  let output = run_pipeline(&code, &args.options).unwrap_or_else(|err| {
+    let filename = args.input.as_deref().unwrap_or("<stdin>");
+    fail(format!("{}:{}:{}: {}", filename, err.line, err.column, err.message), 1)
+  });
+
+  let filename = args.input.as_deref().unwrap_or("<stdin>");
+  for warning in &output.warnings {
+    eprintln!(
+      "{}:{}:{}: warning: {}",
+      filename, warning.line, warning.column, warning.message
+    );
+    for note in &warning.notes {
+      match note.line {
+        0 => eprintln!("  note: {}", note.message),
+        line => eprintln!("  {}:{}:{}: note: {}", filename, line, note.column, note.message),
+      }
+    }
+  }
+
+  let mut code = output.code;
+  if let (Some(path), Some(map)) = (&args.source_map, &output.map) {
+    write(path, map);
+    if args.output.is_some() {
+      let name = Path::new(path).file_name().unwrap_or_default().to_string_lossy();
+      code.push_str(&format!("\n//# sourceMappingURL={}\n", name));
+    }
+  }
+  if let (Some(path), Some(graph)) = (&args.graph, &output.report.graph) {
+    match Path::new(path).extension() {
+      Some(extension) if extension == "dot" => write(path, &graph.to_dot()),
+      _ => write(path, &serde_json::to_string_pretty(graph).unwrap()),
+    }
+  }
+  if let (Some(path), Some(manifest)) = (&args.emit_manifest, &output.report.manifest) {
+    write(path, &serde_json::to_string_pretty(manifest).unwrap());
+  }
+  if let (Some(path), Some(translations)) = (&args.translations, &output.translations) {
+    write(path, &serde_json::to_string_pretty(translations).unwrap());
+  }
+  if let Some(path) = &args.report {
+    write(path, &serde_json::to_string_pretty(&output.report).unwrap());
+  }
+  match &args.output {
+    Some(path) => write(path, &code),
+    None => io::stdout()
+      .write_all(code.as_bytes())
+      .unwrap_or_else(|err| fail(format!("failed to write stdout: {}", err), 2)),
+  }
+}
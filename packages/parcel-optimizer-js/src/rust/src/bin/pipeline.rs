@@ -0,0 +1,48 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::{env, fs, io, process};
+
+use mango_optimizer_js::{run_pipeline, PipelineOptions};
+
+#[derive(Deserialize)]
+struct Input {
+  code: String,
+  #[serde(default)]
+  options: PipelineOptions,
+}
+
+// Usage: mango_optimizer_pipeline [input.json [output.json]]
+// Reads `{ code, options }` and writes either `{ code, map }` or `{ error }`.
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  let input = match args.get(1) {
+    Some(path) => fs::read_to_string(path),
+    None => {
+      let mut input = String::new();
+      io::stdin().read_to_string(&mut input).map(|_| input)
+    }
+  }
+  .unwrap_or_else(|err| {
+    eprintln!("failed to read input: {}", err);
+    process::exit(2);
+  });
+  let input: Input = serde_json::from_str(&input).unwrap_or_else(|err| {
+    eprintln!("invalid input: {}", err);
+    process::exit(2);
+  });
+  let (output, status) = match run_pipeline(&input.code, &input.options) {
+    Ok(output) => (json!(output), 0),
+    Err(error) => (json!({ "error": error }), 1),
+  };
+  let output = output.to_string();
+  let written = match args.get(2) {
+    Some(path) => fs::write(path, output),
+    None => io::stdout().write_all(output.as_bytes()),
+  };
+  if let Err(err) = written {
+    eprintln!("failed to write output: {}", err);
+    process::exit(2);
+  }
+  process::exit(status);
+}
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+use swc_common::{util::take::Take, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::runtime::{is_runtime_call, DESTROY_EFFECT};
+
+pub(crate) struct DeadEffectVisitor<'a> {
+  aliases: &'a HashSet<Id>,
+  dead_effects: &'a HashSet<Id>,
+  references: HashMap<Id, usize>,
+}
+
+impl<'a> DeadEffectVisitor<'a> {
+  pub(crate) fn new(aliases: &'a HashSet<Id>, dead_effects: &'a HashSet<Id>) -> Self {
+    Self {
+      aliases,
+      dead_effects,
+      references: Default::default(),
+    }
+  }
+  pub(crate) fn run(&mut self, program: &mut Program) {
+    if self.dead_effects.is_empty() {
+      return;
+    }
+    program.visit_mut_with(self);
+    let mut references = ReferenceCounter::default();
+    program.visit_with(&mut references);
+    self.references = references.counts;
+    program.visit_mut_with(&mut DeadHandleRemover { visitor: self });
+  }
+  fn is_dead_destroy_call(&self, n: &Expr) -> bool {
+    if let Expr::Call(call_expr) = n {
+      if is_runtime_call(call_expr, self.aliases, DESTROY_EFFECT) {
+        if let Some(ExprOrSpread { expr, spread: None }) = call_expr.args.first() {
+          if let Expr::Ident(handle) = &**expr {
+            return self.dead_effects.contains(&handle.to_id());
+          }
+        }
+      }
+    }
+    false
+  }
+  fn is_unused_handle(&self, n: &VarDeclarator) -> bool {
+    if let Pat::Ident(BindingIdent { id, .. }) = &n.name {
+      let id = id.to_id();
+      return self.dead_effects.contains(&id) && self.references.get(&id).copied().unwrap_or_default() <= 1;
+    }
+    false
+  }
+}
+
+impl VisitMut for DeadEffectVisitor<'_> {
+  fn visit_mut_stmts(&mut self, n: &mut Vec<Stmt>) {
+    n.retain(|stmt| !matches!(stmt, Stmt::Expr(ExprStmt { expr, .. }) if self.is_dead_destroy_call(expr)));
+    n.visit_mut_children_with(self);
+  }
+  fn visit_mut_expr(&mut self, n: &mut Expr) {
+    if self.is_dead_destroy_call(n) {
+      *n = *Expr::undefined(DUMMY_SP);
+      return;
+    }
+    n.visit_mut_children_with(self);
+  }
+}
+
+struct DeadHandleRemover<'a, 'b> {
+  visitor: &'b DeadEffectVisitor<'a>,
+}
+
+impl VisitMut for DeadHandleRemover<'_, '_> {
+  fn visit_mut_stmts(&mut self, n: &mut Vec<Stmt>) {
+    n.visit_mut_children_with(self);
+    let mut new_stmts = Vec::with_capacity(n.len());
+    for stmt in n.drain(..) {
+      match stmt {
+        Stmt::Decl(Decl::Var(mut var_decl)) if var_decl.kind == VarDeclKind::Var => {
+          let mut side_effects = vec![];
+          var_decl.decls.retain_mut(|decl| {
+            if !self.visitor.is_unused_handle(decl) {
+              return true;
+            }
+            match decl.init.take() {
+              Some(mut init) if !is_void_init(&init) => {
+                if let Expr::Call(CallExpr {
+                  callee: Callee::Expr(callee),
+                  ..
+                }) = &mut *init
+                {
+                  if callee.is_fn_expr() {
+                    let expr = callee.take();
+                    **callee = Expr::Paren(ParenExpr { expr, span: DUMMY_SP });
+                  }
+                }
+                side_effects.push(init);
+              }
+              _ => {}
+            }
+            false
+          });
+          if !var_decl.decls.is_empty() {
+            new_stmts.push(Stmt::Decl(Decl::Var(var_decl)));
+          }
+          new_stmts.extend(
+            side_effects
+              .into_iter()
+              .map(|expr| Stmt::Expr(ExprStmt { expr, span: DUMMY_SP })),
+          );
+        }
+        stmt => new_stmts.push(stmt),
+      }
+    }
+    *n = new_stmts;
+  }
+}
+
+fn is_void_init(n: &Expr) -> bool {
+  matches!(n, Expr::Ident(Ident { sym, .. }) if sym == "undefined") || n.is_void()
+}
+
+#[derive(Default)]
+struct ReferenceCounter {
+  counts: HashMap<Id, usize>,
+}
+
+impl Visit for ReferenceCounter {
+  fn visit_ident(&mut self, n: &Ident) {
+    *self.counts.entry(n.to_id()).or_default() += 1;
+  }
+}
+
+#[test]
+fn test_dead_effects() {
+  let output = crate::run_optimizer(
+    r#"
+(function () {
+  var m = mango;
+  var Child = function (props) {
+    "use mango_component";
+    "use 1";
+    var value = props.value;
+    var logger = m.f(function () { console.log(m.b(value)); }, /* EFFECT_DEPS */[value]);
+    var init = m.f(function () { console.log("init", m.b(value)); }, /* IMMEDIATE_EFFECT_DEPS */[value], true);
+    return m.j("div", void 0, [m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.g(logger), m.g(init); }; }]])]);
+  };
+  m.o(document.body, [Child({ value: m.a(1) })]);
+})();
+"#,
+  );
+  assert!(!output.contains("m.g("));
+  assert!(!output.contains("logger"));
+  assert!(!output.contains("var init"));
+  assert!(output.contains(r#"(function(){console.log("init",value);})();"#));
+}
@@ -0,0 +1,343 @@
+use std::collections::{HashMap, HashSet};
+use swc_atoms::Atom;
+use swc_common::comments::Comments;
+use swc_common::errors::HANDLER;
+use swc_common::{Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::annotations::{is_opted_out, opted_out_dep};
+use crate::states::{StateStatus, StateUsageCollector};
+use crate::{passed_props, prop_value_deps, Bailout, ComponentProp};
+
+/// Reports, as warnings, why props that were not folded are still dynamic.
+pub(crate) struct Explainer<'a, C>
+where
+  C: Comments,
+{
+  comments: &'a C,
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  bailouts: &'a HashMap<Id, Bailout>,
+  const_props: &'a HashSet<Id>,
+  stable_states: &'a HashSet<Id>,
+  states: &'a StateUsageCollector<'a, C>,
+  props: HashMap<Id, (Id, Atom)>,
+  dynamic_sites: HashMap<Id, (Span, Id)>,
+}
+
+impl<'a, C> Explainer<'a, C>
+where
+  C: Comments,
+{
+  pub(crate) fn new(
+    comments: &'a C,
+    components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+    bailouts: &'a HashMap<Id, Bailout>,
+    const_props: &'a HashSet<Id>,
+    stable_states: &'a HashSet<Id>,
+    states: &'a StateUsageCollector<'a, C>,
+  ) -> Self {
+    let props = components
+      .iter()
+      .flat_map(|(component, props)| {
+        props
+          .iter()
+          .map(|((name, _), prop)| (prop.local_name.clone(), (component.clone(), name.clone())))
+      })
+      .collect();
+    Self {
+      comments,
+      components,
+      bailouts,
+      const_props,
+      stable_states,
+      states,
+      props,
+      dynamic_sites: Default::default(),
+    }
+  }
+  pub(crate) fn run(&mut self, program: &Program) {
+    program.visit_with(self);
+    let mut bailouts: Vec<_> = self.bailouts.iter().collect();
+    bailouts.sort_by_key(|(_, bailout)| bailout_span(bailout).lo);
+    for (component, bailout) in bailouts {
+      warn(bailout_span(bailout), bailout_message(component, bailout), vec![]);
+    }
+    let mut sites: Vec<_> = self.dynamic_sites.iter().collect();
+    sites.sort_by_key(|(_, (span, _))| span.lo);
+    for (prop, (span, dep)) in sites {
+      let message = format!(
+        "{} is dynamic because the call passes {}",
+        self.describe(prop),
+        self.describe(dep)
+      );
+      warn(*span, message, self.trace(dep));
+    }
+  }
+  fn is_never_changing(&self, id: &Id) -> bool {
+    self.const_props.contains(id) || self.stable_states.contains(id)
+  }
+  fn first_dynamic<'b>(&self, deps: impl IntoIterator<Item = &'b Id>) -> Option<&'b Id> {
+    deps
+      .into_iter()
+      .filter(|dep| !self.is_never_changing(dep))
+      .min_by(|a, b| a.0.cmp(&b.0))
+  }
+  fn describe(&self, id: &Id) -> String {
+    if *id == opted_out_dep() {
+      "a value excluded by a `@mango-no-optimize` pragma".into()
+    } else if let Some((component, name)) = self.props.get(id) {
+      format!("prop `{}` of `{}`", name, component.0)
+    } else if self.states.status(id).is_some() {
+      format!("state `{}`", id.0)
+    } else {
+      format!("`{}`, which is neither a prop nor a state", id.0)
+    }
+  }
+  // Follows the dependency chain of a dynamic value down to the reason it may change.
+  fn trace(&self, dep: &Id) -> Vec<(Span, String)> {
+    let mut notes = vec![];
+    let mut visited: HashSet<&Id> = Default::default();
+    let mut dep = dep;
+    while visited.insert(dep) {
+      if let Some((component, _)) = self.props.get(dep) {
+        if let Some((span, next)) = self.dynamic_sites.get(dep) {
+          let note = format!(
+            "{} is dynamic because it is passed {}",
+            self.describe(dep),
+            self.describe(next)
+          );
+          notes.push((*span, note));
+          dep = next;
+          continue;
+        }
+        if let Some(bailout) = self.bailouts.get(component) {
+          notes.push((bailout_span(bailout), bailout_message(component, bailout)));
+        }
+        break;
+      }
+      match self.states.status(dep) {
+        Some(StateStatus::Escaped(span)) => {
+          notes.push((
+            span,
+            format!(
+              "state `{}` is referenced here outside of getState, so it may change",
+              dep.0
+            ),
+          ));
+        }
+        Some(StateStatus::OpaqueDeps(span)) => {
+          notes.push((
+            span,
+            format!("state `{}` has dependencies that cannot be analyzed", dep.0),
+          ));
+        }
+        Some(StateStatus::Derived(span, deps)) => {
+          if let Some(next) = self.first_dynamic(deps) {
+            notes.push((span, format!("state `{}` depends on {}", dep.0, self.describe(next))));
+            dep = next;
+            continue;
+          }
+        }
+        None => {}
+      }
+      break;
+    }
+    notes
+  }
+}
+
+impl<C> Visit for Explainer<'_, C>
+where
+  C: Comments,
+{
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((component, passed)) = passed_props(n) {
+      if let Some(props) = self.components.get(&component.to_id()) {
+        for (key, value, _) in passed {
+          let Some(prop_info) = props.get(&(key.clone(), Default::default())) else {
+            continue;
+          };
+          if !prop_info.is_used || prop_info.is_escaped || self.const_props.contains(&prop_info.local_name) {
+            continue;
+          }
+          let mut deps = prop_value_deps(value);
+          if is_opted_out(self.comments, n.span) {
+            deps.push(opted_out_dep());
+          }
+          if let Some(dep) = self.first_dynamic(&deps) {
+            self
+              .dynamic_sites
+              .entry(prop_info.local_name.clone())
+              .or_insert((value.span(), dep.clone()));
+          }
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+}
+
+fn bailout_span(bailout: &Bailout) -> Span {
+  match bailout {
+    Bailout::Escaped { span, .. }
+    | Bailout::UnrecognizedPreamble { span }
+    | Bailout::OptedOut { span }
+    | Bailout::Exported { span } => *span,
+  }
+}
+
+fn bailout_message(component: &Id, bailout: &Bailout) -> String {
+  let reason = match bailout {
+    Bailout::Escaped { via_lazy: true, .. } => "the component escapes via createLazyComponent",
+    Bailout::Escaped { via_lazy: false, .. } => "the component is referenced outside of a call",
+    Bailout::UnrecognizedPreamble { .. } => "the shape of its preamble is not recognized",
+    Bailout::OptedOut { .. } => "it is excluded by a `@mango-no-optimize` pragma",
+    Bailout::Exported { .. } => "the component is exported from the module",
+  };
+  format!("props of `{}` are not optimized because {}", component.0, reason)
+}
+
+fn warn(span: Span, message: String, notes: Vec<(Span, String)>) {
+  HANDLER.with(|handler| {
+    let mut diagnostic = handler.struct_span_warn(span, &message);
+    for (span, note) in notes {
+      diagnostic.span_note(span, &note);
+    }
+    diagnostic.emit();
+  });
+}
+
+#[test]
+fn test_explain() {
+  use std::sync::{Arc, Mutex};
+  use swc_common::comments::SingleThreadedComments;
+  use swc_common::errors::{DiagnosticBuilder, Emitter, Handler};
+  use swc_common::{FileName, Mark, SourceMap, GLOBALS};
+  use swc_ecma_parser::{parse_file_as_script, Syntax};
+  use swc_ecma_transforms::resolver;
+
+  use crate::{optimize, Options};
+
+  type Diagnostics = Arc<Mutex<Vec<(usize, String, Vec<(usize, String)>)>>>;
+
+  struct Collector {
+    cm: Arc<SourceMap>,
+    diagnostics: Diagnostics,
+  }
+
+  impl Emitter for Collector {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+      let line = |span: Option<Span>| self.cm.lookup_char_pos(span.unwrap().lo).line;
+      let notes = db
+        .children
+        .iter()
+        .map(|child| (line(child.span.primary_span()), child.message()))
+        .collect();
+      let diagnostic = (line(db.span.primary_span()), db.message(), notes);
+      self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+  }
+
+  let cm: Arc<SourceMap> = Default::default();
+
+  let code = r#"
+(function () {
+  var m = mango;
+  var Label = function (props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return m.j("span", void 0, [text]);
+  };
+  var Card = function (props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    var upper = m.a(function () { return m.b(title).toUpperCase(); }, /* STATE_DEPS */[title]);
+    return m.j("div", void 0, [Label({ text: m.a(function () { return m.b(upper); }, /* STATE_DEPS */[upper]) })]);
+  };
+  var Lazy = function (props) {
+    "use mango_component";
+    "use 1";
+    var size = props.size;
+    return m.j("i", void 0, [size]);
+  };
+  var Broken = function () {
+    "use mango_component";
+    return m.j("b");
+  };
+  var name = m.a("World");
+  m.j("input", [/* DYNAMIC_ATTRS */[function (i) { i.oninput = function () { m.c(name, i.value); }; }]]);
+  m.o(document.body, [Card({ title: name }), m.m("lazy.js", Lazy), Broken()]);
+})();
+"#;
+  let fm = cm.new_source_file(FileName::Anon.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let script = parse_file_as_script(
+    &fm,
+    Syntax::Es(Default::default()),
+    EsVersion::latest(),
+    Some(&comments),
+    &mut vec![],
+  )
+  .expect("failed to parse a js file as a script");
+  let diagnostics = Diagnostics::default();
+  let collector = Collector {
+    cm: cm.clone(),
+    diagnostics: diagnostics.clone(),
+  };
+  let handler = Handler::with_emitter(true, false, Box::new(collector));
+  GLOBALS.set(&Default::default(), || {
+    HANDLER.set(&handler, || {
+      let mut program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+      optimize(
+        &mut program,
+        &comments,
+        &Options {
+          explain: true,
+          ..Default::default()
+        },
+      );
+    });
+  });
+  let diagnostics = diagnostics.lock().unwrap();
+  assert_eq!(
+    *diagnostics,
+    vec![
+      (
+        23,
+        "props of `Broken` are not optimized because the shape of its preamble is not recognized".into(),
+        vec![]
+      ),
+      (
+        29,
+        "props of `Lazy` are not optimized because the component escapes via createLazyComponent".into(),
+        vec![]
+      ),
+      (
+        15,
+        "prop `text` of `Label` is dynamic because the call passes state `upper`".into(),
+        vec![
+          (14, "state `upper` depends on prop `title` of `Card`".into()),
+          (
+            29,
+            "prop `title` of `Card` is dynamic because it is passed state `name`".into()
+          ),
+          (
+            28,
+            "state `name` is referenced here outside of getState, so it may change".into()
+          ),
+        ]
+      ),
+      (
+        29,
+        "prop `title` of `Card` is dynamic because the call passes state `name`".into(),
+        vec![(
+          28,
+          "state `name` is referenced here outside of getState, so it may change".into()
+        )]
+      ),
+    ]
+  );
+}
@@ -0,0 +1,270 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use swc_common::comments::Comments;
+use swc_common::{SourceMap, Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::states::StateUsageCollector;
+use crate::{passed_props, prop_value_deps, ComponentProp};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeKind {
+  Component,
+  Prop,
+  State,
+  /// Any other binding a prop or a state depends on.
+  Binding,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+  pub id: String,
+  pub kind: NodeKind,
+  pub label: String,
+  pub is_constant: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+  pub from: String,
+  pub to: String,
+  #[serde(skip)]
+  pub span: Span,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+/// Components, their props and the states or props these depend on, annotated after propagation.
+#[derive(Serialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyGraph {
+  pub nodes: Vec<GraphNode>,
+  pub edges: Vec<GraphEdge>,
+}
+
+impl DependencyGraph {
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("digraph mango {\n  rankdir=LR;\n");
+    for node in &self.nodes {
+      let shape = match node.kind {
+        NodeKind::Component => "box",
+        NodeKind::Prop => "ellipse",
+        NodeKind::State => "diamond",
+        NodeKind::Binding => "plaintext",
+      };
+      let color = if node.is_constant { "#d4f4dd" } else { "#f8d7da" };
+      writeln!(
+        dot,
+        "  {:?} [label={:?}, shape={}, style=filled, fillcolor={:?}];",
+        node.id, node.label, shape, color
+      )
+      .unwrap();
+    }
+    for edge in &self.edges {
+      match (edge.line, edge.column) {
+        (Some(line), Some(column)) => writeln!(
+          dot,
+          "  {:?} -> {:?} [label=\"{}:{}\"];",
+          edge.from, edge.to, line, column
+        ),
+        _ => writeln!(dot, "  {:?} -> {:?};", edge.from, edge.to),
+      }
+      .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+  }
+  pub(crate) fn locate(&mut self, cm: &SourceMap) {
+    for edge in self.edges.iter_mut().filter(|edge| !edge.span.is_dummy()) {
+      let loc = cm.lookup_char_pos(edge.span.lo);
+      (edge.line, edge.column) = (Some(loc.line), Some(loc.col.0 + 1));
+    }
+  }
+}
+
+fn node_id(id: &Id) -> String {
+  format!("{}#{}", id.0, id.1.as_u32())
+}
+
+/// Records the dependencies of props at call sites and of states before they get pruned.
+#[derive(Default)]
+pub(crate) struct GraphCollector {
+  edges: Vec<(Id, Id, Span)>,
+  states: HashSet<Id>,
+}
+
+impl GraphCollector {
+  pub(crate) fn collect<C: Comments>(
+    program: &Program,
+    components: &HashMap<Id, HashMap<Id, ComponentProp>>,
+    states: &StateUsageCollector<C>,
+  ) -> Self {
+    let mut call_sites = CallSites {
+      components,
+      edges: Default::default(),
+    };
+    program.visit_with(&mut call_sites);
+    let mut collector = Self {
+      edges: call_sites.edges,
+      states: Default::default(),
+    };
+    for (id, span, deps) in states.states() {
+      collector.states.insert(id.clone());
+      for dep in deps {
+        collector.edges.push((id.clone(), dep.clone(), span));
+      }
+    }
+    collector
+  }
+  pub(crate) fn build(
+    self,
+    components: &HashMap<Id, HashMap<Id, ComponentProp>>,
+    const_props: &HashSet<Id>,
+    stable_states: &HashSet<Id>,
+  ) -> DependencyGraph {
+    let mut nodes: HashMap<Id, GraphNode> = Default::default();
+    let mut edges = vec![];
+    for (component, component_props) in components {
+      nodes.insert(
+        component.clone(),
+        GraphNode {
+          id: node_id(component),
+          kind: NodeKind::Component,
+          label: component.0.to_string(),
+          is_constant: component_props
+            .values()
+            .all(|prop| const_props.contains(&prop.local_name)),
+        },
+      );
+      for ((name, _), prop) in component_props {
+        nodes.insert(
+          prop.local_name.clone(),
+          GraphNode {
+            id: node_id(&prop.local_name),
+            kind: NodeKind::Prop,
+            label: format!("{}.{}", component.0, name),
+            is_constant: const_props.contains(&prop.local_name),
+          },
+        );
+        edges.push(GraphEdge {
+          from: node_id(component),
+          to: node_id(&prop.local_name),
+          span: prop.span,
+          line: None,
+          column: None,
+        });
+      }
+    }
+    for state in &self.states {
+      nodes.insert(
+        state.clone(),
+        GraphNode {
+          id: node_id(state),
+          kind: NodeKind::State,
+          label: state.0.to_string(),
+          is_constant: stable_states.contains(state),
+        },
+      );
+    }
+    for (from, to, span) in &self.edges {
+      nodes.entry(to.clone()).or_insert_with(|| GraphNode {
+        id: node_id(to),
+        kind: NodeKind::Binding,
+        label: to.0.to_string(),
+        is_constant: false,
+      });
+      edges.push(GraphEdge {
+        from: node_id(from),
+        to: node_id(to),
+        span: *span,
+        line: None,
+        column: None,
+      });
+    }
+    let mut nodes: Vec<_> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| (a.kind, &a.label, &a.id).cmp(&(b.kind, &b.label, &b.id)));
+    edges.sort_by(|a, b| (a.span.lo, &a.from, &a.to).cmp(&(b.span.lo, &b.from, &b.to)));
+    edges.dedup_by(|a, b| (a.span, &a.from, &a.to) == (b.span, &b.from, &b.to));
+    DependencyGraph { nodes, edges }
+  }
+}
+
+struct CallSites<'a> {
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  edges: Vec<(Id, Id, Span)>,
+}
+
+impl Visit for CallSites<'_> {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((component, passed)) = passed_props(n) {
+      if let Some(props) = self.components.get(&component.to_id()) {
+        for (key, value, _) in passed {
+          if let Some(prop_info) = props.get(&(key.clone(), Default::default())) {
+            for dep in prop_value_deps(value) {
+              self.edges.push((prop_info.local_name.clone(), dep, value.span()));
+            }
+          }
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+}
+
+#[test]
+fn test_dependency_graph() {
+  let (_, report) = crate::run_optimizer_with_report(
+    r#"
+(function () {
+  var m = mango;
+  var Label = function (props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return m.j("span", void 0, [text]);
+  };
+  var Card = function (props) {
+    "use mango_component";
+    "use 2";
+    var title = props.title;
+    var tone = props.tone;
+    return m.j("div", void 0, [Label({ text: m.a(function () { return m.b(title) + m.b(tone); }, /* STATE_DEPS */[title, tone]) })]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Card({ title: m.a("Hi"), tone: count })]);
+})();
+"#,
+    crate::Options {
+      graph: true,
+      ..Default::default()
+    },
+  );
+  let graph = report.graph.unwrap();
+  let node = |label: &str| graph.nodes.iter().find(|node| node.label == label).unwrap();
+  assert_eq!(node("Card").kind, NodeKind::Component);
+  assert!(!node("Card").is_constant);
+  assert!(node("Card.title").is_constant);
+  assert!(!node("Card.tone").is_constant);
+  assert!(!node("Label.text").is_constant);
+  assert_eq!(node("count").kind, NodeKind::State);
+  let has_edge = |from: &str, to: &str| {
+    let (from, to) = (&node(from).id, &node(to).id);
+    graph.edges.iter().any(|edge| &edge.from == from && &edge.to == to)
+  };
+  assert!(has_edge("Card", "Card.title"));
+  assert!(has_edge("Label.text", "Card.title"));
+  assert!(has_edge("Label.text", "Card.tone"));
+  assert!(has_edge("Card.tone", "count"));
+  let dot = graph.to_dot();
+  assert!(dot.starts_with("digraph mango {"));
+  assert!(dot.contains(&format!(
+    "{:?} [label=\"Card.title\", shape=ellipse, style=filled, fillcolor=\"#d4f4dd\"];",
+    node("Card.title").id
+  )));
+}
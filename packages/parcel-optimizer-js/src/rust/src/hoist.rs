@@ -0,0 +1,454 @@
+use std::collections::HashSet;
+use swc_atoms::Atom;
+use swc_common::{util::take::Take, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::exports;
+use crate::runtime::{is_runtime_call, runtime_callee, CLONE_TEMPLATE, CREATE_ELEMENT};
+
+// Properties that are reflected as attributes, hence preserved by `cloneNode`.
+const REFLECTED_PROPS: &[&str] = &[
+  "alt",
+  "className",
+  "colSpan",
+  "dir",
+  "disabled",
+  "height",
+  "hidden",
+  "href",
+  "htmlFor",
+  "id",
+  "lang",
+  "name",
+  "placeholder",
+  "rel",
+  "rowSpan",
+  "src",
+  "tabIndex",
+  "target",
+  "title",
+  "type",
+  "width",
+];
+
+pub(crate) struct StaticHoistVisitor<'a> {
+  aliases: &'a HashSet<Id>,
+  inner_decls: HashSet<Id>,
+  used_names: HashSet<Atom>,
+  available_aliases: HashSet<Id>,
+  templates: Vec<VarDeclarator>,
+  fn_depth: usize,
+}
+
+impl<'a> StaticHoistVisitor<'a> {
+  pub(crate) fn new(aliases: &'a HashSet<Id>, program: &Program) -> Self {
+    let mut used_names = IdentNamesCollector::default();
+    program.visit_with(&mut used_names);
+    Self {
+      aliases,
+      inner_decls: collect_decls::<Id, _>(program).into_iter().collect(),
+      used_names: used_names.names,
+      available_aliases: Default::default(),
+      templates: vec![],
+      fn_depth: 0,
+    }
+  }
+  fn enter_scope(&mut self, scope_decls: HashSet<Id>) {
+    self.available_aliases = self
+      .aliases
+      .iter()
+      .filter(|alias| !self.inner_decls.contains(alias) || scope_decls.contains(alias))
+      .cloned()
+      .collect();
+  }
+  fn templates_decl(&mut self) -> Option<Stmt> {
+    if self.templates.is_empty() {
+      return None;
+    }
+    Some(Stmt::Decl(Decl::Var(Box::new(VarDecl {
+      kind: VarDeclKind::Var,
+      decls: self.templates.take(),
+      ..Default::default()
+    }))))
+  }
+  fn hoist_into(&mut self, stmts: &mut Vec<Stmt>, scope_decls: HashSet<Id>) {
+    self.enter_scope(scope_decls);
+    let mut new_stmts = Vec::with_capacity(stmts.len());
+    for mut stmt in stmts.take() {
+      stmt.visit_mut_with(self);
+      new_stmts.extend(self.templates_decl());
+      new_stmts.push(stmt);
+    }
+    *stmts = new_stmts;
+  }
+  fn next_template_ident(&mut self) -> Ident {
+    let mut idx = 0;
+    loop {
+      let name: Atom = format!("_tpl{}", idx).into();
+      if self.used_names.insert(name.clone()) {
+        return private_ident!(name);
+      }
+      idx += 1;
+    }
+  }
+}
+
+impl VisitMut for StaticHoistVisitor<'_> {
+  fn visit_mut_function(&mut self, n: &mut Function) {
+    self.fn_depth += 1;
+    if self.fn_depth == 1 {
+      if let Some(body) = &mut n.body {
+        let mut scope_decls: HashSet<Id> = find_pat_ids::<_, Id>(&n.params).into_iter().collect();
+        scope_decls.extend(scope_level_decls(&body.stmts));
+        self.hoist_into(&mut body.stmts, scope_decls);
+      }
+    } else {
+      n.visit_mut_children_with(self);
+    }
+    self.fn_depth -= 1;
+  }
+  fn visit_mut_arrow_expr(&mut self, n: &mut ArrowExpr) {
+    self.fn_depth += 1;
+    if self.fn_depth == 1 {
+      if let BlockStmtOrExpr::BlockStmt(body) = &mut *n.body {
+        let mut scope_decls: HashSet<Id> = find_pat_ids::<_, Id>(&n.params).into_iter().collect();
+        scope_decls.extend(scope_level_decls(&body.stmts));
+        self.hoist_into(&mut body.stmts, scope_decls);
+      }
+    } else {
+      n.visit_mut_children_with(self);
+    }
+    self.fn_depth -= 1;
+  }
+  fn visit_mut_script(&mut self, n: &mut Script) {
+    let top_level_decls = scope_level_decls(&n.body);
+    self.inner_decls.retain(|id| !top_level_decls.contains(id));
+    n.visit_mut_children_with(self);
+  }
+  // Components of modules are declared at the top level, so templates are hoisted into the module body.
+  fn visit_mut_module(&mut self, n: &mut Module) {
+    let top_level_decls = module_level_decls(&n.body);
+    self.inner_decls.retain(|id| !top_level_decls.contains(id));
+    self.enter_scope(top_level_decls);
+    self.fn_depth += 1;
+    let mut new_items = Vec::with_capacity(n.body.len());
+    for mut item in n.body.take() {
+      item.visit_mut_with(self);
+      new_items.extend(self.templates_decl().map(ModuleItem::Stmt));
+      new_items.push(item);
+    }
+    n.body = new_items;
+    self.fn_depth -= 1;
+  }
+  fn visit_mut_expr(&mut self, n: &mut Expr) {
+    if is_template(n, self.aliases) {
+      return;
+    }
+    if self.fn_depth > 1 {
+      if let Expr::Call(call_expr) = n {
+        if let Some((alias, _)) = runtime_callee(call_expr) {
+          if self.available_aliases.contains(&alias.to_id()) {
+            if let Some(size) = static_element_size(call_expr, self.aliases) {
+              if size > 1 {
+                let alias = alias.clone();
+                let template_ident = self.next_template_ident();
+                let factory = Expr::Fn(FnExpr {
+                  ident: None,
+                  function: Box::new(Function {
+                    body: Some(BlockStmt {
+                      stmts: vec![Stmt::Return(ReturnStmt {
+                        arg: Some(Box::new(n.take())),
+                        span: DUMMY_SP,
+                      })],
+                      ..Default::default()
+                    }),
+                    ..Default::default()
+                  }),
+                });
+                self.templates.push(VarDeclarator {
+                  span: DUMMY_SP,
+                  name: template_ident.clone().into(),
+                  init: Some(Box::new(Expr::Array(ArrayLit {
+                    elems: vec![Some(factory.into())],
+                    span: DUMMY_SP,
+                  }))),
+                  definite: false,
+                });
+                *n = Expr::Call(CallExpr {
+                  callee: Callee::Expr(Box::new(Expr::Member(MemberExpr {
+                    obj: Box::new(Expr::Ident(alias)),
+                    prop: MemberProp::Ident(IdentName::new(CLONE_TEMPLATE.into(), DUMMY_SP)),
+                    span: DUMMY_SP,
+                  }))),
+                  args: vec![Expr::Ident(template_ident).into()],
+                  ..Default::default()
+                });
+                return;
+              }
+            }
+          }
+        }
+      }
+    }
+    n.visit_mut_children_with(self);
+  }
+}
+
+pub(crate) fn static_element_size(n: &CallExpr, aliases: &HashSet<Id>) -> Option<usize> {
+  if !is_runtime_call(n, aliases, CREATE_ELEMENT) || n.args.iter().any(|arg| arg.spread.is_some()) {
+    return None;
+  }
+  let mut size = 1;
+  match n.args.first().map(|arg| &*arg.expr) {
+    Some(Expr::Lit(Lit::Str(Str { value, .. }))) if value != "script" => {}
+    _ => return None,
+  }
+  match n.args.get(1).map(|arg| &*arg.expr) {
+    None => {}
+    Some(expr) if is_void(expr) => {}
+    Some(Expr::Array(ArrayLit { elems, .. })) => {
+      for elem in elems {
+        if let Some(ExprOrSpread { expr, spread: None }) = elem {
+          if let Expr::Array(ArrayLit { elems, .. }) = &**expr {
+            if let [Some(ExprOrSpread { expr, spread: None })] = &elems[..] {
+              if let Expr::Fn(FnExpr { function, .. }) = &**expr {
+                if is_static_mutator(function) {
+                  size += 1;
+                  continue;
+                }
+              }
+            }
+          }
+        }
+        return None;
+      }
+    }
+    _ => return None,
+  }
+  match n.args.get(2).map(|arg| &*arg.expr) {
+    None => {}
+    Some(expr) if is_void(expr) => {}
+    Some(Expr::Array(ArrayLit { elems, .. })) => size += static_children_size(elems, aliases)?,
+    _ => return None,
+  }
+  match n.args.get(3).map(|arg| &*arg.expr) {
+    None | Some(Expr::Lit(Lit::Num(_))) => {}
+    _ => return None,
+  }
+  if n.args.len() > 4 {
+    return None;
+  }
+  Some(size)
+}
+// Templates hoisted by an earlier run only hold static elements already.
+fn is_template(n: &Expr, aliases: &HashSet<Id>) -> bool {
+  let Expr::Array(ArrayLit { elems, .. }) = n else {
+    return false;
+  };
+  let [Some(ExprOrSpread { expr, spread: None })] = &elems[..] else {
+    return false;
+  };
+  let Expr::Fn(FnExpr { function, .. }) = &**expr else {
+    return false;
+  };
+  match function.body.as_ref().map(|body| &body.stmts[..]) {
+    Some([Stmt::Return(ReturnStmt { arg: Some(arg), .. })]) if function.params.is_empty() => {
+      matches!(&**arg, Expr::Call(call_expr) if static_element_size(call_expr, aliases).is_some())
+    }
+    _ => false,
+  }
+}
+fn static_children_size(elems: &[Option<ExprOrSpread>], aliases: &HashSet<Id>) -> Option<usize> {
+  let mut size = 0;
+  for elem in elems {
+    let Some(ExprOrSpread { expr, spread: None }) = elem else {
+      return None;
+    };
+    match &**expr {
+      Expr::Lit(Lit::Null(_)) => {}
+      Expr::Lit(Lit::Str(_) | Lit::Num(_) | Lit::Bool(_)) => size += 1,
+      Expr::Unary(UnaryExpr { op: op!("!"), arg, .. }) if matches!(&**arg, Expr::Lit(Lit::Num(_))) => size += 1,
+      Expr::Array(ArrayLit { elems, .. }) => size += static_children_size(elems, aliases)?,
+      Expr::Call(call_expr) => size += static_element_size(call_expr, aliases)?,
+      expr if is_void(expr) => {}
+      _ => return None,
+    }
+  }
+  Some(size)
+}
+
+#[derive(Default)]
+struct IdentNamesCollector {
+  names: HashSet<Atom>,
+}
+
+impl Visit for IdentNamesCollector {
+  fn visit_ident(&mut self, n: &Ident) {
+    self.names.insert(n.sym.clone());
+  }
+}
+
+fn module_level_decls(items: &[ModuleItem]) -> HashSet<Id> {
+  let mut decls = HashSet::new();
+  for item in items {
+    match item {
+      ModuleItem::Stmt(stmt) => decls.extend(scope_level_decls(std::slice::from_ref(stmt))),
+      ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+        decls.extend(import.specifiers.iter().map(|specifier| match specifier {
+          ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+          | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+          | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => local.to_id(),
+        }));
+      }
+      ModuleItem::ModuleDecl(decl) => decls.extend(exports(decl).iter().map(|(local, _)| local.to_id())),
+    }
+  }
+  decls
+}
+
+fn scope_level_decls(stmts: &[Stmt]) -> HashSet<Id> {
+  let mut decls = HashSet::new();
+  for stmt in stmts {
+    match stmt {
+      Stmt::Decl(Decl::Var(var_decl)) => {
+        for declarator in &var_decl.decls {
+          decls.extend(find_pat_ids::<_, Id>(&declarator.name));
+        }
+      }
+      Stmt::Decl(Decl::Fn(FnDecl { ident, .. })) => {
+        decls.insert(ident.to_id());
+      }
+      _ => {}
+    }
+  }
+  decls
+}
+
+pub(crate) fn is_void(n: &Expr) -> bool {
+  match n {
+    Expr::Ident(Ident { sym, .. }) => sym == "undefined",
+    Expr::Unary(UnaryExpr {
+      op: op!("void"), arg, ..
+    }) => matches!(&**arg, Expr::Lit(_)),
+    _ => false,
+  }
+}
+
+pub(crate) fn is_static_value(n: &Expr) -> bool {
+  match n {
+    Expr::Lit(Lit::Str(_) | Lit::Num(_) | Lit::Bool(_)) => true,
+    Expr::Unary(UnaryExpr { op: op!("!"), arg, .. }) => matches!(&**arg, Expr::Lit(Lit::Num(_))),
+    _ => false,
+  }
+}
+
+fn is_static_mutator(function: &Function) -> bool {
+  if function.is_async || function.is_generator {
+    return false;
+  }
+  let [Param {
+    pat: Pat::Ident(BindingIdent { id: node, .. }),
+    ..
+  }] = &function.params[..]
+  else {
+    return false;
+  };
+  let Some(body) = &function.body else {
+    return false;
+  };
+  let is_node = |n: &Expr| matches!(n, Expr::Ident(ident) if ident.to_id() == node.to_id());
+  let is_static_mutation = |n: &Expr| match n {
+    Expr::Assign(AssignExpr {
+      op: op!("="),
+      left:
+        AssignTarget::Simple(SimpleAssignTarget::Member(MemberExpr {
+          obj,
+          prop: MemberProp::Ident(prop),
+          ..
+        })),
+      right,
+      ..
+    }) => {
+      if !is_static_value(right) {
+        false
+      } else if is_node(obj) {
+        REFLECTED_PROPS.contains(&&*prop.sym)
+      } else if let Expr::Member(MemberExpr {
+        obj,
+        prop: MemberProp::Ident(style),
+        ..
+      }) = &**obj
+      {
+        is_node(obj) && style.sym == "style"
+      } else {
+        false
+      }
+    }
+    Expr::Call(CallExpr {
+      callee: Callee::Expr(callee),
+      args,
+      ..
+    }) => {
+      if let (
+        Expr::Member(MemberExpr {
+          obj,
+          prop: MemberProp::Ident(method),
+          ..
+        }),
+        [ExprOrSpread {
+          expr: name,
+          spread: None,
+        }, ExprOrSpread {
+          expr: value,
+          spread: None,
+        }],
+      ) = (&**callee, &args[..])
+      {
+        is_node(obj)
+          && method.sym == "setAttribute"
+          && matches!(&**name, Expr::Lit(Lit::Str(_)))
+          && is_static_value(value)
+      } else {
+        false
+      }
+    }
+    _ => false,
+  };
+  body.stmts.iter().all(|stmt| match stmt {
+    Stmt::Expr(ExprStmt { expr, .. }) => match &**expr {
+      Expr::Seq(SeqExpr { exprs, .. }) => exprs.iter().all(|expr| is_static_mutation(expr)),
+      expr => is_static_mutation(expr),
+    },
+    _ => false,
+  })
+}
+
+#[test]
+fn test_static_hoist() {
+  let output = crate::run_optimizer(
+    r#"
+(function () {
+  var m = mango;
+  var Card = function () {
+    return m.j("div", [/* DYNAMIC_ATTRS */[function (i) { i.className = "card"; }]], [
+      m.j("h2", void 0, ["Title"]),
+      m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = alert; }]], ["Go"]),
+      m.j("br")
+    ]);
+  };
+  var Footer = function () {
+    return m.j("footer", [/* DYNAMIC_ATTRS */[function (i) { i.style.color = "red", i.setAttribute("role", "contentinfo"); }]], ["(c)"]);
+  };
+  m.o(document.body, [Card(), Footer(), m.j("p", void 0, ["top-level"])]);
+})();
+"#,
+  );
+  assert!(output.contains(r#"var _tpl0=[function(){return m.j("h2",void 0,["Title"]);}]"#));
+  assert!(output.contains("m.p(_tpl0)"));
+  assert!(output.contains(r#"m.j("button""#));
+  assert!(output.contains(r#"m.j("br")"#));
+  assert!(output.contains("m.p(_tpl1)"));
+  assert!(output.contains(r#"m.j("p",void 0,["top-level"])"#));
+}
@@ -0,0 +1,208 @@
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+use swc_ecma_utils::private_ident;
+
+use crate::runtime::{runtime_callee, CREATE_LAZY_COMPONENT};
+
+const LAZY_COMPONENT_GLOBAL: &str = "__MANGO_COMPONENT__";
+
+fn is_component(function: &Function) -> bool {
+  matches!(
+    function.body.as_ref().and_then(|body| body.stmts.first()),
+    Some(Stmt::Expr(ExprStmt { expr, .. })) if matches!(&**expr, Expr::Lit(Lit::Str(Str { value, .. })) if value == "use mango_component")
+  )
+}
+
+fn is_lazy_component_global(target: &AssignTarget) -> bool {
+  matches!(
+    target,
+    AssignTarget::Simple(SimpleAssignTarget::Member(MemberExpr {
+      obj,
+      prop: MemberProp::Ident(prop),
+      ..
+    })) if prop.sym == LAZY_COMPONENT_GLOBAL && matches!(&**obj, Expr::Ident(window) if window.sym == "window")
+  )
+}
+
+fn wrapper_body(stmt: &mut Stmt) -> Option<&mut Vec<Stmt>> {
+  let Stmt::Expr(ExprStmt { expr, .. }) = stmt else {
+    return None;
+  };
+  let Expr::Assign(AssignExpr {
+    op: op!("="),
+    left,
+    right,
+    ..
+  }) = &mut **expr
+  else {
+    return None;
+  };
+  if !is_lazy_component_global(left) {
+    return None;
+  }
+  let Expr::Call(CallExpr {
+    callee: Callee::Expr(callee),
+    args,
+    ..
+  }) = right.unwrap_parens_mut()
+  else {
+    return None;
+  };
+  match callee.unwrap_parens_mut() {
+    Expr::Fn(FnExpr { function, .. }) if args.is_empty() => Some(&mut function.body.as_mut()?.stmts),
+    _ => None,
+  }
+}
+
+/// Matches the wrapper of lazily loaded component bundles, `window.__MANGO_COMPONENT__ = function () { ...; return C; }()`,
+/// returning the statements before the `return` and the component.
+pub(crate) fn lazy_component_bundle(stmt: &Stmt) -> Option<(&[Stmt], &Ident)> {
+  let Stmt::Expr(ExprStmt { expr, .. }) = stmt else {
+    return None;
+  };
+  let Expr::Assign(AssignExpr {
+    op: op!("="),
+    left,
+    right,
+    ..
+  }) = &**expr
+  else {
+    return None;
+  };
+  if !is_lazy_component_global(left) {
+    return None;
+  }
+  let Expr::Call(CallExpr {
+    callee: Callee::Expr(callee),
+    args,
+    ..
+  }) = right.unwrap_parens()
+  else {
+    return None;
+  };
+  let Expr::Fn(FnExpr { function, .. }) = callee.unwrap_parens() else {
+    return None;
+  };
+  match function.body.as_ref()?.stmts.split_last() {
+    Some((Stmt::Return(ReturnStmt { arg: Some(arg), .. }), stmts)) if args.is_empty() => Some((stmts, arg.as_ident()?)),
+    _ => None,
+  }
+}
+
+/// The minifier inlines the component a lazily loaded bundle returns, so it is declared again to be tracked.
+pub(crate) fn name_lazy_component(program: &mut Program) {
+  let Program::Script(script) = program else {
+    return;
+  };
+  for stmt in &mut script.body {
+    let Some(stmts) = wrapper_body(stmt) else {
+      continue;
+    };
+    if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = stmts.last_mut() {
+      if matches!(&**arg, Expr::Fn(FnExpr { ident: None, function }) if is_component(function)) {
+        let component = private_ident!("Component");
+        let init = std::mem::replace(arg, Box::new(Expr::Ident(component.clone())));
+        let decl = VarDecl {
+          kind: VarDeclKind::Var,
+          decls: vec![VarDeclarator {
+            span: init.span(),
+            name: Pat::Ident(component.into()),
+            init: Some(init),
+            definite: false,
+          }],
+          ..Default::default()
+        };
+        stmts.insert(stmts.len() - 1, Stmt::Decl(Decl::Var(Box::new(decl))));
+      }
+    }
+  }
+}
+
+/// Matches `Mango.m(src, function (c) { return c({ ... }); }, ...)`, returning `src` when it is a literal,
+/// and `c` when the initializer has that shape.
+pub(crate) fn lazy_component_call(n: &CallExpr) -> Option<(Option<&str>, Option<&Ident>)> {
+  if !matches!(runtime_callee(n), Some((_, CREATE_LAZY_COMPONENT))) {
+    return None;
+  }
+  let src = match n.args.first().map(|arg| &*arg.expr) {
+    Some(Expr::Lit(Lit::Str(src))) => Some(src.value.as_str()),
+    _ => None,
+  };
+  let initializer = match n.args.get(1) {
+    Some(ExprOrSpread { expr, spread: None }) => match &**expr {
+      Expr::Fn(FnExpr { function, .. }) => match &function.params[..] {
+        [Param {
+          pat: Pat::Ident(BindingIdent { id, .. }),
+          ..
+        }] => Some(id),
+        _ => None,
+      },
+      _ => None,
+    },
+    _ => None,
+  };
+  Some((src, initializer))
+}
+
+#[test]
+fn test_lazy_components() {
+  use crate::manifest::Manifest;
+
+  let card = r#"
+window.__MANGO_COMPONENT__ = function () {
+  var Mango = mango;
+  return function (props) {
+    "use mango_component";
+    "use 2";
+    var title = props.title;
+    var tone = props.tone;
+    return Mango.j("h2", void 0, [Mango.i(function () { return Mango.b(title) + Mango.b(tone); }, /* DYNAMIC_VIEW_DEPS */[title, tone])]);
+  };
+}();
+"#;
+  let page = |src: &str| {
+    format!(
+      r#"
+(function () {{
+  var Mango = mango;
+  var count = Mango.a(0);
+  Mango.j("button", [/* DYNAMIC_ATTRS */[function (i) {{ i.onclick = function () {{ Mango.c(count, Mango.b(count) + 1); }}; }}]]);
+  Mango.o(document.body, [Mango.m({}, function (c) {{ return c({{ title: Mango.a("Hi"), tone: count }}); }})]);
+}})();
+"#,
+      src
+    )
+  };
+  let emit = |code: &str, module: &str, manifests: Vec<Manifest>| {
+    crate::run_optimizer_with_report(
+      code,
+      crate::Options {
+        module_name: Some(module.into()),
+        emit_manifest: true,
+        manifests,
+        ..Default::default()
+      },
+    )
+  };
+  let static_page = page(r#""/components/card.js""#);
+  let (card_output, card_report) = emit(card, "components/card.js", vec![]);
+  let (_, page_report) = emit(&static_page, "pages/home/page.js", vec![]);
+  assert!(card_output.contains("Mango.b(title)+Mango.b(tone)"));
+  let card_manifest = card_report.manifest.unwrap();
+  let page_manifest = page_report.manifest.unwrap();
+  assert!(card_manifest.components["components/card.js#default"].is_defined);
+  let usage = &page_manifest.components["components/card.js#default"];
+  assert!(!usage.escapes && usage.props["title"].is_constant && !usage.props["tone"].is_constant);
+
+  let manifests = vec![card_manifest.clone(), page_manifest];
+  let (card_output, _) = emit(card, "components/card.js", manifests.clone());
+  let (page_output, _) = emit(&static_page, "pages/home/page.js", manifests);
+  assert!(card_output.contains("return title+Mango.b(tone);},[tone])"));
+  assert!(page_output.contains(r#"c({title:"Hi",tone:count})"#));
+
+  let (_, page_report) = emit(&page(r#""/components/" + name + ".js""#), "pages/home/page.js", vec![]);
+  let page_manifest = page_report.manifest.unwrap();
+  assert!(page_manifest.dynamic_lazy_components);
+  let (card_output, _) = emit(card, "components/card.js", vec![card_manifest, page_manifest]);
+  assert!(card_output.contains("Mango.b(title)+Mango.b(tone)"));
+}
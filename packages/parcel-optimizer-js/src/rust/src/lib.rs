@@ -1,27 +1,92 @@
 #![deny(clippy::all)]
 
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use swc_common::{comments::Comments, Mark, Span, Spanned};
+use swc_atoms::Atom;
+#[cfg(any(feature = "plugin", test))]
+use swc_common::Mark;
+use swc_common::{comments::Comments, errors::HANDLER, Span, Spanned, DUMMY_SP};
 use swc_ecma_ast::*;
+#[cfg(any(feature = "plugin", test))]
 use swc_ecma_transforms::resolver;
 use swc_ecma_utils::*;
 use swc_ecma_visit::*;
+#[cfg(feature = "plugin")]
 use swc_plugin_macro::plugin_transform;
+#[cfg(feature = "plugin")]
 use swc_plugin_proxy::{PluginCommentsProxy, TransformPluginProgramMetadata};
 
-#[derive(Default, Debug)]
+mod annotations;
+mod api;
+mod assertions;
+mod effects;
+mod explain;
+mod graph;
+mod hoist;
+mod lazy;
+mod lint;
+mod manifest;
+mod pipeline;
+mod prerender;
+mod propagation;
+mod report;
+mod runtime;
+mod states;
+mod translations;
+mod verify;
+
+use annotations::{get_annotation, is_opted_out, opted_out_dep, AnnotationStripper};
+pub use api::{analyze, optimize, ComponentGraph, ComponentInfo, PropInfo};
+use assertions::AssertionInjector;
+use effects::DeadEffectVisitor;
+use explain::Explainer;
+use graph::GraphCollector;
+pub use graph::{DependencyGraph, GraphEdge, GraphNode, NodeKind};
+use hoist::StaticHoistVisitor;
+use lazy::{lazy_component_bundle, name_lazy_component};
+pub use lint::LintLevel;
+use lint::{lint_unused_props, UnknownPropLint};
+pub use manifest::{ComponentUsage, Manifest, PropUsage};
+use manifest::{ManifestCollector, ModuleBindings};
+pub use pipeline::{run_pipeline, PipelineError, PipelineOptions, PipelineOutput, PipelineWarning};
+use prerender::PrerenderVisitor;
+use propagation::propagate_constants;
+use report::ComponentLocator;
+pub use report::{Collapse, CollapseKind, ComponentReport, PropReport, PrunedDeps, Report};
+use runtime::{
+  is_runtime_call, runtime_callee, RuntimeAliasCollector, CREATE_EFFECT, CREATE_LAZY_COMPONENT, CREATE_STATE, GET_STATE,
+};
+use states::StateUsageCollector;
+pub use swc_ecma_preset_env::Targets;
+use translations::inline_translations;
+pub use translations::{Dictionary, TranslationCall};
+use verify::{collect_bindings, InvariantChecker};
+
+#[derive(Default, Debug, Clone)]
 struct ComponentProp {
   local_name: Id,
+  span: Span,
   deps: HashSet<Id>,
   common_value: Option<Expr>,
   is_used: bool,
   is_default: bool,
+  is_folded: bool,
+  is_escaped: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Bailout {
+  Escaped { span: Span, via_lazy: bool },
+  UnrecognizedPreamble { span: Span },
+  OptedOut { span: Span },
+  Exported { span: Span },
 }
 
-#[derive(Default, Debug)]
-enum Annotation {
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Annotation {
   #[default]
-  NONE,
+  None,
   EffectDeps,
   ImmediateEffectDeps,
   StateDeps,
@@ -29,16 +94,314 @@ enum Annotation {
   DynamicViewDeps,
 }
 
+const DEFAULT_MAX_ROUNDS: usize = 8;
+
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Options {
+  pub prerender_threshold: Option<usize>,
+  pub max_rounds: Option<usize>,
+  pub measure_sizes: bool,
+  pub explain: bool,
+  pub lint: Option<LintLevel>,
+  pub graph: bool,
+  /// Keeps props assumed constant as states and logs an error at runtime if they are ever set.
+  pub checked: bool,
+  /// Checks the optimized program for structural invariants, reporting violations as errors.
+  pub verify: bool,
+  /// Leaves recognized annotation comments in the output, e.g. to optimize it again.
+  pub keep_annotations: bool,
+  /// Name of the bundle, which keys the components it exports in manifests and resolves its imports.
+  pub module_name: Option<String>,
+  /// Records in the report how the components this bundle exports or imports are used.
+  pub emit_manifest: bool,
+  /// Manifests emitted by a first pass over every bundle of the app. Once merged, props of exported components
+  /// are folded when no other bundle passes them dynamic values.
+  pub manifests: Vec<Manifest>,
+  /// Identifies the build both passes belong to. It is recorded in emitted manifests, and manifests recording
+  /// another one are ignored.
+  pub build_id: Option<String>,
+  /// Locale dictionary to inline `MANGO_TRANSLATION` calls from, for per-locale builds.
+  pub dictionary: Option<Dictionary>,
+  /// Warns about ids of the dictionary the program never uses, which is only meaningful when it is the whole app.
+  pub report_unused_translations: bool,
+}
+
 struct MainVisitor<C>
 where
   C: Comments + Clone,
 {
   comments: C,
   components: HashMap<Id, HashMap<Id, ComponentProp>>,
+  options: Options,
+  report: Report,
 }
 
 struct Round1Visitor<'a> {
+  comments: Option<&'a dyn Comments>,
+  external_usages: &'a HashMap<Id, ComponentUsage>,
   components: &'a mut HashMap<Id, HashMap<Id, ComponentProp>>,
+  bailouts: &'a mut HashMap<Id, Bailout>,
+  discover_components: bool,
+}
+
+impl Round1Visitor<'_> {
+  fn escape(&mut self, component: &Ident, via_lazy: bool) {
+    self.bail_out(
+      component,
+      Bailout::Escaped {
+        span: component.span,
+        via_lazy,
+      },
+    );
+  }
+  fn bail_out(&mut self, component: &Ident, bailout: Bailout) {
+    if let Some(props) = self.components.get_mut(&component.to_id()) {
+      for prop in props.values_mut() {
+        prop.is_escaped = true;
+        prop.is_used = true;
+      }
+      self.bailouts.entry(component.to_id()).or_insert(bailout);
+    }
+  }
+  fn discover_component(&mut self, id: &Ident, function: &Function) {
+    if let Some(block_stmt) = &function.body {
+      let is_component = if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.first() {
+        if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
+          value == "use mango_component"
+        } else {
+          false
+        }
+      } else {
+        false
+      };
+      if is_component {
+        let mut props_info: HashMap<Id, ComponentProp> = Default::default();
+        if let Some(props_count) = preamble_props_count(block_stmt) {
+          if props_count > 0 {
+            let mut curr_decl_pos = 2;
+            let mut prop_pos = 0;
+            'outer_loop: while prop_pos < props_count {
+              while !matches!(&block_stmt.stmts.get(curr_decl_pos), Some(Stmt::Decl(Decl::Var(_)))) {
+                curr_decl_pos += 1;
+                prop_pos += 1;
+                if prop_pos > props_count {
+                  break 'outer_loop;
+                }
+              }
+              if let Some(Stmt::Decl(Decl::Var(n))) = &block_stmt.stmts.get(curr_decl_pos) {
+                for declaration in &n.decls {
+                  if let VarDeclarator {
+                    name: Pat::Ident(BindingIdent { id, .. }),
+                    init: Some(init),
+                    ..
+                  } = declaration
+                  {
+                    // Check if logical expression
+                    if let Expr::Bin(BinExpr {
+                      left, op: op!("||"), ..
+                    }) = &**init
+                    {
+                      if let Expr::Member(MemberExpr {
+                        prop: MemberProp::Ident(left_prop),
+                        ..
+                      }) = &**left
+                      {
+                        let prop_info = ComponentProp {
+                          local_name: id.to_id(),
+                          span: declaration.span,
+                          is_default: true,
+                          ..Default::default()
+                        };
+                        props_info.insert((left_prop.sym.clone(), Default::default()), prop_info);
+                        prop_pos += 2;
+                      }
+                    } else if let Expr::Member(MemberExpr {
+                      prop: MemberProp::Ident(left_prop),
+                      ..
+                    }) = &**init
+                    {
+                      let prop_info = ComponentProp {
+                        local_name: id.to_id(),
+                        span: declaration.span,
+                        is_default: false,
+                        ..Default::default()
+                      };
+                      props_info.insert((left_prop.sym.clone(), Default::default()), prop_info);
+                      prop_pos += 1;
+                    }
+                  }
+                  if prop_pos >= props_count {
+                    break 'outer_loop;
+                  }
+                }
+              }
+              curr_decl_pos += 1;
+            }
+          }
+          self.components.insert(id.to_id(), props_info);
+        } else {
+          self
+            .bailouts
+            .insert(id.to_id(), Bailout::UnrecognizedPreamble { span: function.span });
+        }
+      }
+    }
+  }
+  fn apply_usage(&mut self, component: &Ident, usage: &ComponentUsage) {
+    if let Some(props) = self.components.get_mut(&component.to_id()) {
+      for ((name, _), prop) in props.iter_mut() {
+        if let Some(prop_usage) = usage.props.get(name.as_str()) {
+          prop.is_used |= prop_usage.is_used;
+          prop.is_escaped |= !prop_usage.is_constant;
+        }
+      }
+    }
+  }
+  fn export(&mut self, component: &Ident) {
+    match self.external_usages.get(&component.to_id()) {
+      Some(usage) if !usage.escapes => self.apply_usage(component, usage),
+      _ => self.bail_out(component, Bailout::Exported { span: component.span }),
+    }
+  }
+  fn is_opted_out(&self, span: Span) -> bool {
+    self.comments.is_some_and(|comments| is_opted_out(comments, span))
+  }
+}
+
+fn preamble_props_count(block_stmt: &BlockStmt) -> Option<i32> {
+  if let Some(Stmt::Expr(ExprStmt { expr, .. })) = block_stmt.stmts.get(1) {
+    if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
+      return value.strip_prefix("use ")?.parse().ok();
+    }
+  }
+  None
+}
+
+/// Local bindings exported by a module declaration along with their exported names, which other modules may call or set.
+fn exports(n: &ModuleDecl) -> Vec<(Ident, Atom)> {
+  let default = || Atom::from("default");
+  match n {
+    ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => match decl {
+      Decl::Var(var_decl) => find_pat_ids::<_, Ident>(&var_decl.decls)
+        .into_iter()
+        .map(|ident| (ident.clone(), ident.sym))
+        .collect(),
+      Decl::Fn(FnDecl { ident, .. }) | Decl::Class(ClassDecl { ident, .. }) => vec![(ident.clone(), ident.sym.clone())],
+      _ => vec![],
+    },
+    ModuleDecl::ExportNamed(NamedExport {
+      specifiers, src: None, ..
+    }) => specifiers
+      .iter()
+      .filter_map(|specifier| match specifier {
+        ExportSpecifier::Named(ExportNamedSpecifier {
+          orig: ModuleExportName::Ident(orig),
+          exported,
+          ..
+        }) => Some((orig.clone(), exported.as_ref().map_or(orig.sym.clone(), export_name))),
+        _ => None,
+      })
+      .collect(),
+    ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+      decl:
+        DefaultDecl::Fn(FnExpr { ident: Some(ident), .. }) | DefaultDecl::Class(ClassExpr { ident: Some(ident), .. }),
+      ..
+    }) => vec![(ident.clone(), default())],
+    ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) => expr
+      .as_ident()
+      .map(|ident| (ident.clone(), default()))
+      .into_iter()
+      .collect(),
+    _ => vec![],
+  }
+}
+
+fn export_name(name: &ModuleExportName) -> Atom {
+  match name {
+    ModuleExportName::Ident(ident) => ident.sym.clone(),
+    ModuleExportName::Str(str) => str.value.clone(),
+  }
+}
+
+fn prop_key(key: &PropName) -> Option<(&Atom, Span)> {
+  match key {
+    PropName::Ident(key) => Some((&key.sym, key.span)),
+    PropName::Str(key) => Some((&key.value, key.span)),
+    _ => None,
+  }
+}
+
+/// Matches a call to an identifier, returning the callee along with the key, value and key span of each prop
+/// passed in an object literal as the first argument.
+fn passed_props(n: &CallExpr) -> Option<(&Ident, impl Iterator<Item = (&Atom, &Expr, Span)>)> {
+  let Callee::Expr(callee) = &n.callee else {
+    return None;
+  };
+  let Expr::Ident(component) = &**callee else {
+    return None;
+  };
+  let props = match n.args.first().map(|arg| &*arg.expr) {
+    Some(Expr::Object(object)) => &object.props[..],
+    _ => &[],
+  };
+  let props = props
+    .iter()
+    .filter_map(|prop| match prop.as_prop().map(|prop| &**prop) {
+      Some(Prop::KeyValue(KeyValueProp { key, value })) => {
+        let (key, span) = prop_key(key)?;
+        Some((key, &**value, span))
+      }
+      _ => None,
+    });
+  Some((component, props))
+}
+
+fn passed_props_mut(n: &mut CallExpr) -> Option<(&Ident, impl Iterator<Item = (&Atom, &mut Expr, Span)>)> {
+  let CallExpr {
+    callee: Callee::Expr(callee),
+    args,
+    ..
+  } = n
+  else {
+    return None;
+  };
+  let Expr::Ident(component) = &**callee else {
+    return None;
+  };
+  let props = match args.first_mut().map(|arg| &mut *arg.expr) {
+    Some(Expr::Object(object)) => &mut object.props[..],
+    _ => &mut [],
+  };
+  let props = props.iter_mut().filter_map(|prop| match prop {
+    PropOrSpread::Prop(prop) => match &mut **prop {
+      Prop::KeyValue(KeyValueProp { key, value }) => {
+        let (key, span) = prop_key(key)?;
+        Some((key, &mut **value, span))
+      }
+      _ => None,
+    },
+    PropOrSpread::Spread(_) => None,
+  });
+  Some((component, props))
+}
+
+fn prop_value_deps(value: &Expr) -> Vec<Id> {
+  if let Expr::Call(expr) = value {
+    if let Some(ExprOrSpread { expr: n, .. }) = &expr.args.get(1) {
+      if let Expr::Array(expr) = &**n {
+        return expr
+          .elems
+          .iter()
+          .flatten()
+          .filter_map(|ExprOrSpread { expr: n, .. }| n.as_ident().map(|ident| ident.to_id()))
+          .collect();
+      }
+    }
+  } else if let Expr::Ident(ident) = value {
+    return vec![ident.to_id()];
+  }
+  vec![]
 }
 
 struct Round2Visitor<'a, C>
@@ -47,46 +410,116 @@ where
 {
   comments: C,
   components: &'a mut HashMap<Id, HashMap<Id, ComponentProp>>,
+  aliases: &'a HashSet<Id>,
   const_props_usages: HashMap<Id, bool>,
+  stable_states: HashSet<Id>,
+  dead_effects: HashSet<Id>,
+  rewrite_components: bool,
+  report: &'a mut Report,
 }
 
 impl<C> Round2Visitor<'_, C>
 where
   C: Comments,
 {
-  fn get_annotation(&mut self, span: Span) -> Annotation {
-    if let Some(comments) = self.comments.get_leading(span.lo()) {
-      if let Some(comment) = comments.first() {
-        return if comment.text == " EFFECT_DEPS " {
-          Annotation::EffectDeps
-        } else if comment.text == " IMMEDIATE_EFFECT_DEPS " {
-          Annotation::ImmediateEffectDeps
-        } else if comment.text == " STATE_DEPS " {
-          Annotation::StateDeps
-        } else if comment.text == " DYNAMIC_ATTRS " {
-          Annotation::DynamicAttrs
-        } else if comment.text == " DYNAMIC_VIEW_DEPS " {
-          Annotation::DynamicViewDeps
+  fn rewrite_component(&self, id: &Ident, function: &mut Function) {
+    if let Some(block_stmt) = &mut function.body {
+      let is_component: bool = if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.first() {
+        if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
+          value == "use mango_component"
         } else {
-          Annotation::NONE
-        };
+          false
+        }
+      } else {
+        false
+      };
+      if is_component {
+        if let Some(props_info) = self.components.get(&id.to_id()) {
+          if let Some(props_count) = preamble_props_count(block_stmt) {
+            if props_count > 0 {
+              let mut curr_decl_pos = 2;
+              let mut prop_pos = 0;
+              'outer_loop: while prop_pos < props_count {
+                while !matches!(&block_stmt.stmts.get(curr_decl_pos), Some(Stmt::Decl(Decl::Var(_)))) {
+                  curr_decl_pos += 1;
+                  prop_pos += 1;
+                  if prop_pos > props_count {
+                    break 'outer_loop;
+                  }
+                }
+                if let Some(Stmt::Decl(Decl::Var(n))) = &mut block_stmt.stmts.get_mut(curr_decl_pos) {
+                  let mut pending_is_default = false;
+                  for declaration in &mut n.decls {
+                    if let VarDeclarator { init: Some(init), .. } = declaration {
+                      if pending_is_default {
+                        **init = Expr::Ident(quote_ident!("false").into());
+                        pending_is_default = false;
+                      } else if let Expr::Bin(BinExpr {
+                        left,
+                        op: op!("||"),
+                        right,
+                        ..
+                      }) = &**init
+                      {
+                        if let Expr::Member(MemberExpr {
+                          prop: MemberProp::Ident(left_prop),
+                          ..
+                        }) = &**left
+                        {
+                          if let Some(prop_info) = props_info.get(&(left_prop.sym.clone(), Default::default())) {
+                            if !prop_info.is_used {
+                              *init = right.clone();
+                              pending_is_default = true;
+                              prop_pos += 1;
+                            } else {
+                              prop_pos += 2;
+                            }
+                          }
+                        }
+                      } else if let Expr::Member(MemberExpr {
+                        prop: MemberProp::Ident(left_prop),
+                        ..
+                      }) = &**init
+                      {
+                        if let Some(prop_info) = props_info.get(&(left_prop.sym.clone(), Default::default())) {
+                          if !prop_info.is_used {
+                            **init = Expr::Ident(quote_ident!("undefined").into());
+                          }
+                          prop_pos += 1;
+                        }
+                      }
+                    }
+                    if prop_pos >= props_count {
+                      break 'outer_loop;
+                    }
+                  }
+                }
+                curr_decl_pos += 1;
+              }
+            }
+          }
+        }
       }
-      return Annotation::NONE;
     }
-    return Annotation::NONE;
   }
-  fn clean_deps_array(&mut self, array_expr: &ArrayLit) -> Vec<Option<ExprOrSpread>> {
+  fn get_annotation(&mut self, span: Span) -> Annotation {
+    get_annotation(&self.comments, span)
+  }
+  fn is_never_changing(&self, id: &Id) -> bool {
+    self.const_props_usages.contains_key(id) || self.stable_states.contains(id)
+  }
+  fn clean_deps_array(&mut self, array_expr: &ArrayLit, annotation: Annotation) -> Vec<Option<ExprOrSpread>> {
     let mut new_elems = vec![];
     for elem in &array_expr.elems {
       if let Some(ExprOrSpread { expr: n, .. }) = elem {
         if let Expr::Cond(CondExpr { cons, .. }) = &**n {
           if let Expr::Ident(ident) = &**cons {
-            if !self.const_props_usages.contains_key(&ident.to_id()) {
+            if !self.is_never_changing(&ident.to_id()) {
               new_elems.push(elem.clone());
             }
           }
         } else if let Expr::Ident(ident) = &**n {
-          if !self.const_props_usages.contains_key(&ident.to_id()) {
+          if !self.is_never_changing(&ident.to_id()) {
             new_elems.push(elem.clone());
           }
         } else {
@@ -94,7 +527,10 @@ where
         }
       }
     }
-    return new_elems;
+    self
+      .report
+      .record_pruned(annotation, array_expr.span, array_expr.elems.len(), new_elems.len());
+    new_elems
   }
 }
 
@@ -103,45 +539,193 @@ where
   C: Comments + Clone,
 {
   fn visit_mut_program(&mut self, expr: &mut Program) {
-    expr.visit_children_with(&mut Round1Visitor {
-      components: &mut self.components,
-    });
-    let mut const_props_usages: HashMap<Id, bool> = Default::default();
-    loop {
-      let old_size = const_props_usages.len();
-      const_props_usages = self
-        .components
-        .iter()
-        .flat_map(|(_, props)| {
-          props
+    let mut runtime_aliases = RuntimeAliasCollector {
+      comments: &self.comments,
+      aliases: Default::default(),
+    };
+    expr.visit_with(&mut runtime_aliases);
+    let max_rounds = self.options.max_rounds.unwrap_or(DEFAULT_MAX_ROUNDS).max(1);
+    let mut all_const_props: HashSet<Id> = Default::default();
+    let mut all_stable_states: HashSet<Id> = Default::default();
+    let mut dead_effects: HashSet<Id> = Default::default();
+    let mut original_sizes: HashMap<Id, usize> = Default::default();
+    let mut bailouts: HashMap<Id, Bailout> = Default::default();
+    let mut graph_collector = None;
+    name_lazy_component(expr);
+    if let Some(dictionary) = &self.options.dictionary {
+      inline_translations(expr, dictionary, self.options.report_unused_translations);
+    }
+    let bindings_before = self.options.verify.then(|| collect_bindings(expr));
+    let module_name = self.options.module_name.clone().unwrap_or_default();
+    let mut external_usages: HashMap<Id, ComponentUsage> = Default::default();
+    let mut imported_components: HashSet<Id> = Default::default();
+    let (manifests, stale_manifests): (Vec<_>, Vec<_>) = self
+      .options
+      .manifests
+      .iter()
+      .partition(|manifest| manifest.build_id == self.options.build_id);
+    if !stale_manifests.is_empty() && HANDLER.is_set() {
+      HANDLER.with(|handler| {
+        handler
+          .struct_warn(&format!(
+            "ignoring {} manifest(s) emitted by another build",
+            stale_manifests.len()
+          ))
+          .emit();
+      });
+    }
+    if !manifests.is_empty() {
+      let mut manifest = Manifest::default();
+      for other in manifests {
+        manifest.merge(other);
+      }
+      let bindings = ModuleBindings::collect(expr, &module_name);
+      external_usages = bindings.usages(&manifest);
+      // Imported components are defined elsewhere, so only their call sites are rewritten.
+      for (id, usage) in &external_usages {
+        if bindings.imports.contains_key(id) && usage.is_defined && !usage.escapes {
+          let props = usage
+            .props
             .iter()
-            .filter(|(_, prop)| prop.deps.is_empty() || !prop.is_used || !prop.common_value.is_none())
-            .map(|(_, prop)| (prop.local_name.clone(), prop.is_default))
-        })
-        .collect();
-      let new_size = const_props_usages.len();
-      if new_size == old_size {
+            .map(|(name, prop_usage)| {
+              let prop = ComponentProp {
+                local_name: private_ident!(name.as_str()).to_id(),
+                is_escaped: !prop_usage.is_constant,
+                ..Default::default()
+              };
+              ((Atom::from(name.as_str()), Default::default()), prop)
+            })
+            .collect();
+          self.components.insert(id.clone(), props);
+          imported_components.insert(id.clone());
+        }
+      }
+    }
+    self.report = Default::default();
+    while self.report.rounds < max_rounds {
+      let is_first_round = self.report.rounds == 0;
+      for props in self.components.values_mut() {
+        for prop in props.values_mut().filter(|prop| !prop.is_folded) {
+          prop.is_used = false;
+          prop.deps.clear();
+        }
+      }
+      expr.visit_children_with(&mut Round1Visitor {
+        comments: Some(&self.comments),
+        external_usages: &external_usages,
+        components: &mut self.components,
+        bailouts: &mut bailouts,
+        discover_components: is_first_round,
+      });
+      if is_first_round && self.options.measure_sizes {
+        let mut locator = ComponentLocator::new(&self.components, true);
+        expr.visit_with(&mut locator);
+        original_sizes = locator
+          .found
+          .into_iter()
+          .filter_map(|(id, _, size)| Some((id, size?)))
+          .collect();
+      }
+      let mut state_usages = StateUsageCollector::new(&self.comments, &runtime_aliases.aliases);
+      expr.visit_with(&mut state_usages);
+      if is_first_round && self.options.graph {
+        graph_collector = Some(GraphCollector::collect(expr, &self.components, &state_usages));
+      }
+      let (mut const_props_usages, mut stable_states) =
+        propagate_constants(&mut self.components, state_usages.stable_candidates());
+      const_props_usages.retain(|id, _| !all_const_props.contains(id));
+      stable_states.retain(|id| !all_stable_states.contains(id));
+      if !is_first_round && const_props_usages.is_empty() && stable_states.is_empty() {
         break;
       }
-      for (_, props) in &mut self.components {
-        for (_, prop) in props {
-          if !prop.deps.is_empty() {
-            let mut new_deps = HashSet::new();
-            for dep in &prop.deps {
-              if !const_props_usages.contains_key(dep) {
-                new_deps.insert(dep.clone());
-              }
-            }
-            prop.deps = new_deps;
-          }
+      all_const_props.extend(const_props_usages.keys().cloned());
+      all_stable_states.extend(stable_states.iter().cloned());
+      if self.options.checked {
+        self.report.rounds += 1;
+        break;
+      }
+      let mut round2_visitor = Round2Visitor {
+        comments: self.comments.clone(),
+        components: &mut self.components,
+        aliases: &runtime_aliases.aliases,
+        const_props_usages,
+        stable_states,
+        dead_effects: Default::default(),
+        rewrite_components: is_first_round,
+        report: &mut self.report,
+      };
+      expr.visit_mut_children_with(&mut round2_visitor);
+      dead_effects.extend(round2_visitor.dead_effects);
+      for props in self.components.values_mut() {
+        for prop in props.values_mut() {
+          prop.is_folded = all_const_props.contains(&prop.local_name);
         }
       }
+      self.report.rounds += 1;
+    }
+    DeadEffectVisitor::new(&runtime_aliases.aliases, &dead_effects).run(expr);
+    if self.options.checked {
+      expr.visit_mut_with(&mut AssertionInjector::new(
+        &self.components,
+        &all_const_props,
+        &runtime_aliases.aliases,
+      ));
+    }
+    if let Some(threshold) = self.options.prerender_threshold {
+      expr.visit_mut_with(&mut PrerenderVisitor {
+        aliases: &runtime_aliases.aliases,
+        threshold,
+      });
+    }
+    let mut static_hoist_visitor = StaticHoistVisitor::new(&runtime_aliases.aliases, expr);
+    expr.visit_mut_with(&mut static_hoist_visitor);
+    let mut locator = ComponentLocator::new(&self.components, self.options.measure_sizes);
+    expr.visit_with(&mut locator);
+    self
+      .report
+      .add_components(&self.components, &all_const_props, &locator, &original_sizes);
+    self.report.graph =
+      graph_collector.map(|collector| collector.build(&self.components, &all_const_props, &all_stable_states));
+    if let Some(bindings_before) = bindings_before.filter(|_| HANDLER.is_set()) {
+      InvariantChecker::new(
+        &self.comments,
+        &runtime_aliases.aliases,
+        &self.components,
+        &bindings_before,
+      )
+      .run(expr);
+    }
+    if self.options.emit_manifest {
+      self.report.manifest = Some(Manifest {
+        build_id: self.options.build_id.clone(),
+        ..ManifestCollector::new(&module_name, &all_const_props, &all_stable_states).collect(
+          expr,
+          &self.components,
+          &bailouts,
+        )
+      });
+    }
+    self.components.retain(|id, _| !imported_components.contains(id));
+    if self.options.explain && HANDLER.is_set() {
+      let mut state_usages = StateUsageCollector::new(&self.comments, &runtime_aliases.aliases);
+      expr.visit_with(&mut state_usages);
+      Explainer::new(
+        &self.comments,
+        &self.components,
+        &bailouts,
+        &all_const_props,
+        &all_stable_states,
+        &state_usages,
+      )
+      .run(expr);
+    }
+    if let Some(level) = self.options.lint.filter(|_| HANDLER.is_set()) {
+      expr.visit_with(&mut UnknownPropLint::new(&self.components, level));
+      lint_unused_props(&self.components, level);
+    }
+    if !self.options.keep_annotations {
+      expr.visit_with(&mut AnnotationStripper::new(&self.comments));
     }
-    expr.visit_mut_children_with(&mut Round2Visitor {
-      comments: self.comments.clone(),
-      const_props_usages,
-      components: &mut self.components,
-    });
   }
 }
 
@@ -152,74 +736,69 @@ where
   fn visit_mut_array_lit(&mut self, n: &mut ArrayLit) {
     n.visit_mut_children_with(self);
     let array_annotation = self.get_annotation(n.span);
-    if let Annotation::StateDeps = array_annotation {
-      n.elems = self.clean_deps_array(n);
-    } else if let Annotation::DynamicAttrs = array_annotation {
-      n.elems = self.clean_deps_array(n);
+    if let Annotation::StateDeps | Annotation::DynamicAttrs = array_annotation {
+      n.elems = self.clean_deps_array(n, array_annotation);
     }
   }
   fn visit_mut_call_expr(&mut self, n: &mut CallExpr) {
     n.visit_mut_children_with(self);
-    let callee = &n.callee;
-    let params = &mut n.args;
-    if let Callee::Expr(callee) = callee {
-      if let Expr::Ident(callee) = &**callee {
-        if self.components.contains_key(&callee.to_id()) {
-          if let Some(component_info) = self.components.get_mut(&callee.to_id()) {
-            if let Some(ExprOrSpread { expr: n, .. }) = &mut params.get_mut(0) {
-              if let Expr::Object(n) = &mut **n {
-                for prop in &mut n.props {
-                  if let PropOrSpread::Prop(prop) = prop {
-                    if let Prop::KeyValue(prop) = &mut **prop {
-                      if let PropName::Ident(prop_name) = &prop.key {
-                        if let Some(prop_info) = component_info.get_mut(&(prop_name.sym.clone(), Default::default())) {
-                          if prop_info.deps.is_empty() {
-                            if let Expr::Call(expr) = &*prop.value {
-                              if let Some(ExprOrSpread { expr: n, .. }) = &expr.args.get(0) {
-                                if let Expr::Fn(FnExpr { function, .. }) = &**n {
-                                  if let Some(block_stmt) = &function.body {
-                                    if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) =
-                                      &block_stmt.stmts.get(0)
-                                    {
-                                      *prop.value = *arg.clone();
-                                    }
-                                  }
-                                } else {
-                                  *prop.value = *n.clone();
-                                }
-                              }
-                            }
-                          } else if let Expr::Ident(ident) = &*prop.value {
-                            if let Some(_) = self.const_props_usages.get(&ident.to_id()) {
-                              *prop.value = Expr::Array(ArrayLit {
-                                elems: vec![Some(ExprOrSpread {
-                                  expr: Box::new(Expr::Ident(ident.clone())),
-                                  spread: None,
-                                })],
-                                span: prop.value.span(),
-                              });
-                            }
-                          }
-                        }
-                      }
-                    }
-                  }
+    let Some((callee, props)) = passed_props_mut(n) else {
+      return;
+    };
+    let Some(component_info) = self.components.get_mut(&callee.to_id()) else {
+      return;
+    };
+    for (key, value, _) in props {
+      let Some(prop_info) = component_info
+        .get_mut(&(key.clone(), Default::default()))
+        .filter(|prop_info| !prop_info.is_folded && !prop_info.is_escaped)
+      else {
+        continue;
+      };
+      if prop_info.deps.is_empty() {
+        if let Some(expr) = match &*value {
+          Expr::Call(expr) if is_runtime_call(expr, self.aliases, CREATE_STATE) => Some(expr),
+          _ => None,
+        } {
+          if let Some(ExprOrSpread { expr: n, .. }) = &expr.args.first() {
+            if let Expr::Fn(FnExpr { function, .. }) = &**n {
+              if let Some(block_stmt) = &function.body {
+                if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = &block_stmt.stmts.first() {
+                  *value = *arg.clone();
                 }
               }
+            } else {
+              *value = *n.clone();
+            }
+          }
+        } else if let Expr::Array(ArrayLit { elems, .. }) = &*value {
+          if let [Some(ExprOrSpread { expr: n, spread: None })] = &elems[..] {
+            if n.is_ident() {
+              *value = *n.clone();
             }
           }
         }
+      } else if let Expr::Ident(ident) = &*value {
+        if self.const_props_usages.contains_key(&ident.to_id()) {
+          *value = Expr::Array(ArrayLit {
+            elems: vec![Some(ExprOrSpread {
+              expr: Box::new(Expr::Ident(ident.clone())),
+              spread: None,
+            })],
+            span: value.span(),
+          });
+        }
       }
     }
   }
   fn visit_mut_expr(&mut self, n: &mut Expr) {
     let mut should_return = false;
     if let Expr::Cond(CondExpr { cons, .. }) = &n {
-      if let Expr::Call(call_expr) = &**cons {
+      if let Some(call_expr) = Expr::as_call(cons).filter(|n| is_runtime_call(n, self.aliases, GET_STATE)) {
         let params = &call_expr.args;
         if let Some(ExprOrSpread {
           expr: pot_state_expr, ..
-        }) = &params.get(0)
+        }) = &params.first()
         {
           if let Expr::Ident(pot_state) = &**pot_state_expr {
             if self.const_props_usages.get(&pot_state.to_id()).eq(&Some(&true)) {
@@ -229,11 +808,11 @@ where
           }
         }
       }
-    } else if let Expr::Call(call_expr) = &n {
+    } else if let Some(call_expr) = Expr::as_call(n).filter(|n| is_runtime_call(n, self.aliases, GET_STATE)) {
       let params = &call_expr.args;
       if let Some(ExprOrSpread {
         expr: pot_state_expr, ..
-      }) = &params.get(0)
+      }) = &params.first()
       {
         if let Expr::Ident(pot_state) = &**pot_state_expr {
           if self.const_props_usages.contains_key(&pot_state.to_id()) {
@@ -251,11 +830,12 @@ where
       let params = &mut call_expr.args;
       if let Some(ExprOrSpread { expr, .. }) = &mut params.get_mut(1) {
         if let Expr::Array(array_expr) = &mut **expr {
-          if array_expr.elems.len() > 0 {
+          if !array_expr.elems.is_empty() {
             let array_annotation = self.get_annotation(array_expr.span);
             if let Annotation::EffectDeps = array_annotation {
-              let new_elems = self.clean_deps_array(array_expr);
-              if new_elems.len() == 0 {
+              let new_elems = self.clean_deps_array(array_expr, array_annotation);
+              if new_elems.is_empty() {
+                self.report.record_collapse(CollapseKind::Effect, call_expr.span);
                 *n = Expr::Ident(quote_ident!("undefined").into());
               } else {
                 *array_expr = ArrayLit {
@@ -264,9 +844,12 @@ where
                 };
               }
             } else if let Annotation::ImmediateEffectDeps = array_annotation {
-              let new_elems = self.clean_deps_array(array_expr);
-              if new_elems.len() == 0 {
-                if let Some(ExprOrSpread { expr: effect, .. }) = &params.get(0) {
+              let new_elems = self.clean_deps_array(array_expr, array_annotation);
+              if new_elems.is_empty() {
+                if let Some(ExprOrSpread { expr: effect, .. }) = &params.first() {
+                  self
+                    .report
+                    .record_collapse(CollapseKind::ImmediateEffect, call_expr.span);
                   *n = Expr::Call(CallExpr {
                     callee: Callee::Expr(effect.clone()),
                     args: vec![],
@@ -282,12 +865,13 @@ where
                 };
               }
             } else if let Annotation::DynamicViewDeps = array_annotation {
-              let new_elems = self.clean_deps_array(array_expr);
-              if new_elems.len() == 0 {
-                if let Some(ExprOrSpread { expr: view, .. }) = &params.get(0) {
+              let new_elems = self.clean_deps_array(array_expr, array_annotation);
+              if new_elems.is_empty() {
+                if let Some(ExprOrSpread { expr: view, .. }) = &params.first() {
                   if let Expr::Fn(FnExpr { function, .. }) = &**view {
                     if let Some(block_stmt) = &function.body {
-                      if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = &block_stmt.stmts.get(0) {
+                      if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = &block_stmt.stmts.first() {
+                        self.report.record_collapse(CollapseKind::DynamicView, call_expr.span);
                         *n = *arg.clone();
                       }
                     }
@@ -311,12 +895,13 @@ where
               if let Expr::Array(array_expr) = &mut **expr {
                 let array_annotation = self.get_annotation(array_expr.span);
                 if let Annotation::DynamicViewDeps = array_annotation {
-                  let new_elems = self.clean_deps_array(array_expr);
-                  if new_elems.len() == 0 {
+                  let new_elems = self.clean_deps_array(array_expr, array_annotation);
+                  if new_elems.is_empty() {
                     if let Some(Some(ExprOrSpread { expr: view, .. })) = &outer_array_expr.elems.get_mut(0) {
                       if let Expr::Fn(FnExpr { function, .. }) = &**view {
                         if let Some(block_stmt) = &function.body {
-                          if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = &block_stmt.stmts.get(0) {
+                          if let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = &block_stmt.stmts.first() {
+                            self.report.record_collapse(CollapseKind::DynamicView, function.span);
                             *n = Expr::Array(ArrayLit {
                               elems: vec![Some(ExprOrSpread {
                                 expr: arg.clone(),
@@ -343,387 +928,342 @@ where
     }
   }
   fn visit_mut_var_declarator(&mut self, n: &mut VarDeclarator) {
+    let is_effect = |init: &Option<Box<Expr>>, aliases| matches!(init.as_deref(), Some(Expr::Call(call_expr)) if is_runtime_call(call_expr, aliases, CREATE_EFFECT));
+    let was_effect = is_effect(&n.init, self.aliases);
     n.visit_mut_children_with(self);
-    if let Some(expr) = &mut n.init {
-      if let Expr::Fn(FnExpr { function, .. }) = &mut **expr {
-        if let Some(block_stmt) = &mut function.body {
-          let is_component: bool = if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.get(0) {
-            if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
-              value == "use mango_component"
-            } else {
-              false
-            }
-          } else {
-            false
-          };
-          if is_component {
-            if let Pat::Ident(BindingIdent { id, .. }) = &n.name {
-              if let Some(props_info) = self.components.get(&id.to_id()) {
-                if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.get(1) {
-                  if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
-                    let props_count = value[4..].parse::<i32>().unwrap();
-                    if props_count > 0 {
-                      let mut curr_decl_pos = 2;
-                      let mut prop_pos = 0;
-                      'outer_loop: while prop_pos < props_count {
-                        while !matches!(&block_stmt.stmts.get(curr_decl_pos), Some(Stmt::Decl(Decl::Var(_)))) {
-                          curr_decl_pos += 1;
-                          prop_pos += 1;
-                          if prop_pos > props_count {
-                            break 'outer_loop;
-                          }
-                        }
-                        if let Some(Stmt::Decl(Decl::Var(n))) = &mut block_stmt.stmts.get_mut(curr_decl_pos) {
-                          let mut pending_is_default = false;
-                          for declaration in &mut n.decls {
-                            if let VarDeclarator { init: Some(init), .. } = declaration {
-                              if pending_is_default {
-                                *init = Box::new(Expr::Ident(quote_ident!("false").into()));
-                                pending_is_default = false;
-                              } else if let Expr::Bin(BinExpr {
-                                left,
-                                op: op!("||"),
-                                right,
-                                ..
-                              }) = &**init
-                              {
-                                if let Expr::Member(MemberExpr {
-                                  prop: MemberProp::Ident(left_prop),
-                                  ..
-                                }) = &**left
-                                {
-                                  if let Some(prop_info) = props_info.get(&(left_prop.sym.clone(), Default::default()))
-                                  {
-                                    if !prop_info.is_used {
-                                      *init = right.clone();
-                                      pending_is_default = true;
-                                      prop_pos += 1;
-                                    } else {
-                                      prop_pos += 2;
-                                    }
-                                  }
-                                }
-                              } else if let Expr::Member(MemberExpr {
-                                prop: MemberProp::Ident(left_prop),
-                                ..
-                              }) = &**init
-                              {
-                                if let Some(prop_info) = props_info.get(&(left_prop.sym.clone(), Default::default())) {
-                                  if !prop_info.is_used {
-                                    *init = Box::new(Expr::Ident(quote_ident!("undefined").into()));
-                                  }
-                                  prop_pos += 1;
-                                }
-                              }
-                            }
-                            if prop_pos >= props_count {
-                              break 'outer_loop;
-                            }
-                          }
-                        }
-                        curr_decl_pos += 1;
-                      }
-                    }
-                  }
-                }
-              }
-            }
-          }
-        }
+    if was_effect && !is_effect(&n.init, self.aliases) {
+      if let Pat::Ident(BindingIdent { id, .. }) = &n.name {
+        self.dead_effects.insert(id.to_id());
+      }
+    }
+    if !self.rewrite_components {
+      return;
+    }
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&n.name, &mut n.init) {
+      if let Expr::Fn(FnExpr { function, .. }) = &mut **init {
+        self.rewrite_component(id, function);
       }
     }
   }
+  fn visit_mut_fn_decl(&mut self, n: &mut FnDecl) {
+    n.visit_mut_children_with(self);
+    if self.rewrite_components {
+      self.rewrite_component(&n.ident, &mut n.function);
+    }
+  }
+  fn visit_mut_default_decl(&mut self, n: &mut DefaultDecl) {
+    n.visit_mut_children_with(self);
+    if let (
+      true,
+      DefaultDecl::Fn(FnExpr {
+        ident: Some(ident),
+        function,
+      }),
+    ) = (self.rewrite_components, n)
+    {
+      self.rewrite_component(ident, function);
+    }
+  }
 }
 
 impl Visit for Round1Visitor<'_> {
+  fn visit_module_decl(&mut self, n: &ModuleDecl) {
+    // `export default Component` is an export rather than an escaping reference.
+    if !matches!(n, ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) if expr.is_ident()) {
+      n.visit_children_with(self);
+    }
+    for (component, _) in exports(n) {
+      self.export(&component);
+    }
+  }
+  fn visit_script(&mut self, n: &Script) {
+    for stmt in &n.body {
+      match lazy_component_bundle(stmt) {
+        Some((stmts, component)) => {
+          stmts.visit_with(self);
+          self.export(component);
+        }
+        None => stmt.visit_with(self),
+      }
+    }
+  }
   fn visit_call_expr(&mut self, n: &CallExpr) {
-    let callee = &n.callee;
-    let params = &n.args;
-    if let Callee::Expr(n) = callee {
-      if let Expr::Ident(component) = &**n {
-        if self.components.contains_key(&component.to_id()) {
-          if let Some(component_info) = self.components.get_mut(&component.to_id()) {
-            if let Some(ExprOrSpread { expr: n, .. }) = &params.get(0) {
-              if let Expr::Object(n) = &**n {
-                for prop in &n.props {
-                  if let PropOrSpread::Prop(prop) = prop {
-                    if let Prop::KeyValue(prop) = &**prop {
-                      if let PropName::Ident(prop_name) = &prop.key {
-                        if let Some(prop_info) = component_info.get_mut(&(prop_name.sym.clone(), Default::default())) {
-                          prop_info.is_used = true;
-                          if let Expr::Call(expr) = &*prop.value {
-                            if let Some(ExprOrSpread { expr: n, .. }) = &expr.args.get(1) {
-                              if let Expr::Array(expr) = &**n {
-                                for elem in &expr.elems {
-                                  if let Some(ExprOrSpread { expr: n, .. }) = elem {
-                                    if let Expr::Ident(ident) = &**n {
-                                      prop_info.deps.insert(ident.to_id());
-                                    }
-                                  }
-                                }
-                              }
-                            }
-                          } else if let Expr::Ident(ident) = &*prop.value {
-                            prop_info.deps.insert(ident.to_id());
-                          }
-                        }
-                      }
-                    }
-                  }
-                }
-              }
+    if let Some((component, props)) =
+      passed_props(n).filter(|(component, _)| self.components.contains_key(&component.to_id()))
+    {
+      let is_opted_out = self.is_opted_out(n.span);
+      if let Some(component_info) = self.components.get_mut(&component.to_id()) {
+        for (key, value, _) in props {
+          if let Some(prop_info) = component_info
+            .get_mut(&(key.clone(), Default::default()))
+            .filter(|prop_info| !prop_info.is_folded)
+          {
+            prop_info.is_used = true;
+            prop_info.deps.extend(prop_value_deps(value));
+            if is_opted_out {
+              prop_info.deps.insert(opted_out_dep());
             }
           }
         }
       }
+      n.args.visit_with(self);
+      return;
+    }
+    if let Some((_, CREATE_LAZY_COMPONENT)) = runtime_callee(n) {
+      for arg in &n.args {
+        if let Expr::Ident(component) = &*arg.expr {
+          self.escape(component, true);
+        }
+      }
     }
     n.visit_children_with(self);
   }
-  fn visit_var_declarator(&mut self, n: &VarDeclarator) {
-    if let Some(expr) = &n.init {
-      if let Expr::Fn(FnExpr { function, .. }) = &**expr {
-        if let Some(block_stmt) = &function.body {
-          let is_component = if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.get(0) {
-            if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
-              value == "use mango_component"
-            } else {
-              false
-            }
-          } else {
-            false
-          };
-          if is_component {
-            if let Pat::Ident(BindingIdent { id, .. }) = &n.name {
-              let mut props_info: HashMap<Id, ComponentProp> = Default::default();
-              if let Some(Stmt::Expr(ExprStmt { expr, .. })) = &block_stmt.stmts.get(1) {
-                if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
-                  let props_count = value[4..].parse::<i32>().unwrap();
-                  if props_count > 0 {
-                    let mut curr_decl_pos = 2;
-                    let mut prop_pos = 0;
-                    'outer_loop: while prop_pos < props_count {
-                      while !matches!(&block_stmt.stmts.get(curr_decl_pos), Some(Stmt::Decl(Decl::Var(_)))) {
-                        curr_decl_pos += 1;
-                        prop_pos += 1;
-                        if prop_pos > props_count {
-                          break 'outer_loop;
-                        }
-                      }
-                      if let Some(Stmt::Decl(Decl::Var(n))) = &block_stmt.stmts.get(curr_decl_pos) {
-                        for declaration in &n.decls {
-                          if let VarDeclarator {
-                            name: Pat::Ident(BindingIdent { id, .. }),
-                            init: Some(init),
-                            ..
-                          } = declaration
-                          {
-                            // Check if logical expression
-                            if let Expr::Bin(BinExpr {
-                              left, op: op!("||"), ..
-                            }) = &**init
-                            {
-                              if let Expr::Member(MemberExpr {
-                                prop: MemberProp::Ident(left_prop),
-                                ..
-                              }) = &**left
-                              {
-                                let prop_info = ComponentProp {
-                                  local_name: id.to_id(),
-                                  is_default: true,
-                                  ..Default::default()
-                                };
-                                props_info.insert((left_prop.sym.clone(), Default::default()), prop_info);
-                                prop_pos += 2;
-                              }
-                            } else if let Expr::Member(MemberExpr {
-                              prop: MemberProp::Ident(left_prop),
-                              ..
-                            }) = &**init
-                            {
-                              let prop_info = ComponentProp {
-                                local_name: id.to_id(),
-                                is_default: false,
-                                ..Default::default()
-                              };
-                              props_info.insert((left_prop.sym.clone(), Default::default()), prop_info);
-                              prop_pos += 1;
-                            }
-                          }
-                          if prop_pos >= props_count {
-                            break 'outer_loop;
-                          }
-                        }
-                      }
-                      curr_decl_pos += 1;
-                    }
-                  }
-                  self.components.insert(id.to_id(), props_info);
-                }
-              };
-            };
-          }
+  fn visit_expr(&mut self, n: &Expr) {
+    if let Expr::Ident(ident) = n {
+      self.escape(ident, false);
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_var_decl(&mut self, n: &VarDecl) {
+    n.visit_children_with(self);
+    for decl in &n.decls {
+      if let Pat::Ident(BindingIdent { id, .. }) = &decl.name {
+        let init_span = decl.init.as_ref().map_or(DUMMY_SP, |init| init.span());
+        if self.is_opted_out(n.span) || self.is_opted_out(decl.span) || self.is_opted_out(init_span) {
+          self.bail_out(id, Bailout::OptedOut { span: decl.span });
         }
       }
     }
+  }
+  fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+    if let (true, Pat::Ident(BindingIdent { id, .. }), Some(init)) = (self.discover_components, &n.name, &n.init) {
+      if let Expr::Fn(FnExpr { function, .. }) = &**init {
+        self.discover_component(id, function);
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_fn_decl(&mut self, n: &FnDecl) {
+    if self.discover_components {
+      self.discover_component(&n.ident, &n.function);
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_default_decl(&mut self, n: &DefaultDecl) {
+    if let (
+      true,
+      DefaultDecl::Fn(FnExpr {
+        ident: Some(ident),
+        function,
+      }),
+    ) = (self.discover_components, n)
+    {
+      self.discover_component(ident, function);
+    }
     n.visit_children_with(self);
   }
 }
 
+#[cfg(feature = "plugin")]
 #[plugin_transform]
-pub fn process_transform(mut program: Program, _metadata: TransformPluginProgramMetadata) -> Program {
-  let pass = &mut (
-    resolver(Mark::new(), Mark::new(), false),
-    visit_mut_pass(MainVisitor {
-      comments: PluginCommentsProxy,
-      components: Default::default(),
-    }),
-  );
-  program.mutate(pass);
+pub fn process_transform(mut program: Program, metadata: TransformPluginProgramMetadata) -> Program {
+  let options = metadata
+    .get_transform_plugin_config()
+    .and_then(|config| serde_json::from_str(&config).ok())
+    .unwrap_or_default();
+  program.mutate(&mut resolver(Mark::new(), Mark::new(), false));
+  optimize(&mut program, &PluginCommentsProxy, &options);
   program
 }
 
-#[test]
-fn test_transform() {
+#[cfg(test)]
+fn run_optimizer(code: &str) -> String {
+  run_optimizer_with_config(code, Default::default())
+}
+
+#[cfg(test)]
+fn run_optimizer_with_config(code: &str, options: Options) -> String {
+  run_optimizer_with_report(code, options).0
+}
+
+#[cfg(test)]
+fn run_optimizer_with_report(code: &str, options: Options) -> (String, Report) {
+  run_optimizer_on(code, options, false)
+}
+
+#[cfg(test)]
+fn run_module_optimizer(code: &str, options: Options) -> (String, Report) {
+  run_optimizer_on(code, options, true)
+}
+
+#[cfg(test)]
+fn run_optimizer_on(code: &str, options: Options, module: bool) -> (String, Report) {
   use std::sync::Arc;
-  use std::time::Instant;
 
-  use swc_core::common::comments::SingleThreadedComments;
-  use swc_core::common::{FileName, SourceMap, GLOBALS};
-  use swc_ecma_codegen::{text_writer::JsWriter, Config, Emitter};
-  use swc_ecma_minifier::optimize;
-  use swc_ecma_minifier::option::{CompressOptions, ExtraOptions, MangleOptions, MinifyOptions};
-  use swc_ecma_parser::{parse_file_as_script, Syntax};
-  use swc_ecma_transforms::fixer::fixer;
-
-  #[no_mangle]
-  fn transform(code: String) -> String {
-    let cm: Arc<SourceMap> = Arc::default();
-    let fm = cm.new_source_file(FileName::Anon.into(), code.clone());
-    let comments = SingleThreadedComments::default();
-
-    let mut errors = vec![];
-    let res = parse_file_as_script(
-      &fm,
-      Syntax::Es(Default::default()),
-      EsVersion::latest(),
-      Some(&comments),
-      &mut errors,
-    );
+  use swc_common::comments::SingleThreadedComments;
+  use swc_common::{FileName, SourceMap, GLOBALS};
+  use swc_ecma_codegen::{text_writer::JsWriter, Emitter};
+  use swc_ecma_parser::{parse_file_as_module, parse_file_as_script, Syntax};
 
-    let program = match res {
-      Ok(v) => Program::Script(v),
-      Err(_) => panic!("failed to parse a js file as a script"),
+  let cm: Arc<SourceMap> = Arc::default();
+  let fm = cm.new_source_file(FileName::Anon.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let syntax = Syntax::Es(Default::default());
+  let program = if module {
+    Program::Module(
+      parse_file_as_module(&fm, syntax, EsVersion::latest(), Some(&comments), &mut vec![])
+        .expect("failed to parse a js file as a module"),
+    )
+  } else {
+    Program::Script(
+      parse_file_as_script(&fm, syntax, EsVersion::latest(), Some(&comments), &mut vec![])
+        .expect("failed to parse a js file as a script"),
+    )
+  };
+
+  let mut src = vec![];
+  let mut report = Default::default();
+  GLOBALS.set(&Default::default(), || {
+    let mut program = program.apply(&mut resolver(Mark::new(), Mark::new(), false));
+    report = optimize(&mut program, &comments, &options);
+    let mut emitter = Emitter {
+      cfg: swc_ecma_codegen::Config::default().with_minify(true),
+      comments: None,
+      cm: cm.clone(),
+      wr: Box::new(JsWriter::new(cm.clone(), "\n", &mut src, None)),
     };
+    emitter.emit_program(&program).unwrap();
+  });
+  (String::from_utf8(src).unwrap(), report)
+}
 
-    let mut output = "".to_string();
-
-    GLOBALS.set(&Default::default(), || {
-      let unresolved_mark = Mark::new();
-      let top_level_mark = Mark::new();
-
-      let start = Instant::now();
-      let program = program.apply(&mut resolver(unresolved_mark, top_level_mark, false));
-      let duration = start.elapsed();
-      println!("Resolver: {:?}", duration);
-
-      let start = Instant::now();
-      let mut program = optimize(
-        program,
-        cm.clone(),
-        Some(&comments),
-        None,
-        &MinifyOptions {
-          rename: false,
-          compress: Some(CompressOptions {
-            passes: 2,
-            keep_fargs: false,
-            negate_iife: false,
-            side_effects: true,
-            props: false,
-            directives: false,
-            ie8: true,
-            ..Default::default()
-          }),
-          mangle: Some(MangleOptions {
-            ie8: true,
-            ..Default::default()
-          }),
-          wrap: false,
-          enclose: false,
-        },
-        &ExtraOptions {
-          unresolved_mark,
-          top_level_mark,
-          mangle_name_cache: Default::default(),
-        },
-      );
-      let duration = start.elapsed();
-      println!("Minifier: {:?}", duration);
-
-      let start = Instant::now();
-      let mut visitor = MainVisitor {
-        comments: comments.clone(),
-        components: Default::default(),
-      };
+#[cfg(test)]
+fn run_optimizer_with_diagnostics(code: &str, options: Options) -> Vec<String> {
+  use std::sync::{Arc, Mutex};
 
-      visitor.visit_mut_program(&mut program);
-      let duration = start.elapsed();
-      println!("Optimizer: {:?}", duration);
-
-      let start = Instant::now();
-      let program = optimize(
-        program,
-        cm.clone(),
-        None,
-        None,
-        &MinifyOptions {
-          rename: false,
-          compress: Some(CompressOptions {
-            passes: 2,
-            keep_fargs: false,
-            negate_iife: false,
-            side_effects: true,
-            props: false,
-            directives: true,
-            ie8: true,
-            ..Default::default()
-          }),
-          mangle: Some(MangleOptions {
-            ie8: true,
-            ..Default::default()
-          }),
-          wrap: false,
-          enclose: false,
-        },
-        &ExtraOptions {
-          unresolved_mark,
-          top_level_mark,
-          mangle_name_cache: Default::default(),
-        },
-      );
-      let duration = start.elapsed();
-      println!("Minifier2: {:?}", duration);
-
-      let program: Program = program.apply(&mut fixer(Some(&comments as &dyn Comments)));
-
-      let mut src = vec![];
-      let mut emitter = Emitter {
-        cfg: Config::default().with_minify(true),
-        comments: Some(&comments),
-        cm: cm.clone(),
-        wr: Box::new(JsWriter::new(cm.clone(), "\n", &mut src, None)),
-      };
+  use swc_common::comments::SingleThreadedComments;
+  use swc_common::errors::{DiagnosticBuilder, Emitter, Handler};
+  use swc_common::{FileName, SourceMap, GLOBALS};
+  use swc_ecma_parser::{parse_file_as_script, Syntax};
 
-      emitter.emit_program(&program).unwrap();
-      output = String::from_utf8(src).unwrap();
-    });
-    return output;
+  struct Collector(Arc<Mutex<Vec<String>>>);
+
+  impl Emitter for Collector {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+      let mut diagnostic = format!("{}: {}", db.level, db.message());
+      for child in &db.children {
+        diagnostic.push_str(&format!(" ({}: {})", child.level, child.message()));
+      }
+      self.0.lock().unwrap().push(diagnostic);
+    }
   }
 
+  let cm: Arc<SourceMap> = Arc::default();
+  let fm = cm.new_source_file(FileName::Anon.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let script = parse_file_as_script(
+    &fm,
+    Syntax::Es(Default::default()),
+    EsVersion::latest(),
+    Some(&comments),
+    &mut vec![],
+  )
+  .expect("failed to parse a js file as a script");
+  let diagnostics: Arc<Mutex<Vec<String>>> = Default::default();
+  let handler = Handler::with_emitter(true, false, Box::new(Collector(diagnostics.clone())));
+  GLOBALS.set(&Default::default(), || {
+    HANDLER.set(&handler, || {
+      let mut program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+      optimize(&mut program, &comments, &options);
+    });
+  });
+  let diagnostics = diagnostics.lock().unwrap().clone();
+  diagnostics
+}
+
+#[test]
+fn test_global_fixpoint() {
   let code = r#"
-var a = 50;
+(function () {
+  var m = mango;
+  var Label = function (props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /* DYNAMIC_VIEW_DEPS */[text])]);
+  };
+  var Card = function (props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    var heading = m.a("");
+    var sync = m.f(function () { m.c(heading, m.b(title)); }, /* EFFECT_DEPS */[title]);
+    return m.j("div", void 0, [Label({ text: m.a(function () { return m.b(heading) + "!"; }, /* STATE_DEPS */[heading]) })]);
+  };
+  m.o(document.body, [Card({ title: m.a("Hello") })]);
+})();
 "#;
+  let (output, report) = run_optimizer_with_report(code, Default::default());
+  assert_eq!(report.rounds, 2);
+  assert!(output.contains(r#"Label({text:m.b(heading)+"!"})"#));
+  assert!(output.contains(r#"m.j("span",void 0,[text])"#));
+  let (output, report) = run_optimizer_with_report(
+    code,
+    Options {
+      max_rounds: Some(1),
+      ..Default::default()
+    },
+  );
+  assert_eq!(report.rounds, 1);
+  assert!(output.contains("m.i(function(){return m.b(text);},[text])"));
+}
 
-  let result = transform(code.to_string());
-  println!("{}", result);
+#[test]
+fn test_no_optimize_pragma() {
+  let code = r#"
+(function () {
+  var m = mango;
+  /* @mango-no-optimize */
+  var Card = function (props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    return m.j("h1", void 0, [m.i(function () { return m.b(title); }, /* DYNAMIC_VIEW_DEPS */[title])]);
+  };
+  var Badge = function (props) {
+    "use mango_component";
+    "use 2";
+    var text = props.text;
+    var tone = props.tone;
+    return m.j("span", void 0, [m.i(function () { return m.b(text) + m.b(tone); }, /* DYNAMIC_VIEW_DEPS */[text, tone])]);
+  };
+  var Label = function (props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /* DYNAMIC_VIEW_DEPS */[text])]);
+  };
+  m.o(document.body, [Card({ title: m.a("Hi") }), Badge({ text: m.a("a"), tone: m.a("info") }), /* @mango-no-optimize */ Badge({ text: m.a("b") }), Label({ text: m.a("c") })]);
+})();
+"#;
+  let output = run_optimizer(code);
+  assert!(output.contains(r#"Card({title:m.a("Hi")})"#));
+  assert!(output.contains(r#"Badge({text:m.a("a"),tone:"info"})"#));
+  assert!(output.contains(r#"Badge({text:m.a("b")})"#));
+  assert!(output.contains("return m.b(text)+tone;},[text])"));
+  assert!(output.contains(r#"Label({text:"c"})"#));
+  let diagnostics = run_optimizer_with_diagnostics(
+    code,
+    Options {
+      explain: true,
+      ..Default::default()
+    },
+  );
+  assert!(diagnostics.contains(
+    &"warning: props of `Card` are not optimized because it is excluded by a `@mango-no-optimize` pragma".to_string()
+  ));
+  assert!(diagnostics.contains(
+    &"warning: prop `text` of `Badge` is dynamic because the call passes a value excluded by a `@mango-no-optimize` pragma"
+      .to_string()
+  ));
 }
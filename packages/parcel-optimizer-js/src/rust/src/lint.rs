@@ -0,0 +1,206 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use swc_atoms::Atom;
+use swc_common::errors::{DiagnosticBuilder, Handler, HANDLER};
+use swc_common::Span;
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::{passed_props, ComponentProp};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LintLevel {
+  Warn,
+  /// Reports lints as errors, failing the build.
+  Error,
+}
+
+fn report<'a>(handler: &'a Handler, level: LintLevel, span: Span, message: &str) -> DiagnosticBuilder<'a> {
+  match level {
+    LintLevel::Warn => handler.struct_span_warn(span, message),
+    LintLevel::Error => handler.struct_span_err(span, message),
+  }
+}
+
+/// Reports props passed at call sites that the called component does not declare.
+pub(crate) struct UnknownPropLint<'a> {
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  level: LintLevel,
+}
+
+impl<'a> UnknownPropLint<'a> {
+  pub(crate) fn new(components: &'a HashMap<Id, HashMap<Id, ComponentProp>>, level: LintLevel) -> Self {
+    Self { components, level }
+  }
+}
+
+impl Visit for UnknownPropLint<'_> {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((component, passed)) = passed_props(n) {
+      if let Some(props) = self.components.get(&component.to_id()) {
+        for (name, _, span) in passed {
+          if props.contains_key(&(name.clone(), Default::default())) {
+            continue;
+          }
+          let message = format!("`{}` is not a prop of `{}`", name, component.sym);
+          HANDLER.with(|handler| {
+            let mut diagnostic = report(handler, self.level, span, &message);
+            if let Some(suggestion) = suggest(name, props.keys().map(|(name, _)| name)) {
+              diagnostic.span_help(span, &format!("did you mean `{}`?", suggestion));
+            } else if !props.is_empty() {
+              let mut declared: Vec<_> = props.keys().map(|(name, _)| format!("`{}`", name)).collect();
+              declared.sort();
+              diagnostic.note(&format!("`{}` declares {}", component.sym, declared.join(", ")));
+            }
+            diagnostic.emit();
+          });
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+}
+
+/// Reports props that no caller passes and that have no default, grouped per component.
+pub(crate) fn lint_unused_props(components: &HashMap<Id, HashMap<Id, ComponentProp>>, level: LintLevel) {
+  let mut unused: Vec<(&Id, Vec<(&Atom, Span)>)> = components
+    .iter()
+    .map(|(component, props)| {
+      let mut props: Vec<_> = props
+        .iter()
+        .filter(|(_, prop)| !prop.is_used && !prop.is_default)
+        .map(|((name, _), prop)| (name, prop.span))
+        .collect();
+      props.sort_by_key(|(_, span)| span.lo);
+      (component, props)
+    })
+    .filter(|(_, props)| !props.is_empty())
+    .collect();
+  unused.sort_by_key(|(_, props)| props[0].1.lo);
+  for (component, props) in unused {
+    let names: Vec<_> = props.iter().map(|(name, _)| format!("`{}`", name)).collect();
+    let message = format!(
+      "`{}` declares props that no caller passes and that have no default: {}",
+      component.0,
+      names.join(", ")
+    );
+    HANDLER.with(|handler| {
+      let mut diagnostic = report(handler, level, props[0].1, &message);
+      for (name, span) in &props {
+        diagnostic.span_note(*span, &format!("`{}` is always undefined", name));
+      }
+      diagnostic.emit();
+    });
+  }
+}
+
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a Atom>) -> Option<&'a Atom> {
+  let max_distance = (name.chars().count() / 3).max(1);
+  candidates
+    .map(|candidate| (edit_distance(name, candidate), candidate))
+    .filter(|(distance, _)| *distance <= max_distance)
+    .min()
+    .map(|(_, candidate)| candidate)
+}
+
+// Optimal string alignment distance, so that swapped letters count as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+  for (i, row) in distances.iter_mut().enumerate() {
+    row[0] = i;
+  }
+  distances[0] = (0..=b.len()).collect();
+  for i in 1..=a.len() {
+    for j in 1..=b.len() {
+      let cost = usize::from(a[i - 1] != b[j - 1]);
+      let mut distance = (distances[i - 1][j] + 1)
+        .min(distances[i][j - 1] + 1)
+        .min(distances[i - 1][j - 1] + cost);
+      if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+        distance = distance.min(distances[i - 2][j - 2] + 1);
+      }
+      distances[i][j] = distance;
+    }
+  }
+  distances[a.len()][b.len()]
+}
+
+#[test]
+fn test_unknown_props() {
+  let code = r#"
+(function () {
+  var m = mango;
+  var Card = function (props) {
+    "use mango_component";
+    "use 2";
+    var title = props.title;
+    var body = props.body;
+    return m.j("div", void 0, [title, body]);
+  };
+  m.o(document.body, [Card({ tittle: m.a("Hi"), body: m.a("Text") }), Card({ titel: m.a("Hi"), body: m.a("x"), footer: m.a("x") }), Card({ title: m.a("Ok"), body: m.a("Ok") })]);
+})();
+"#;
+  let options = crate::Options {
+    lint: Some(LintLevel::Warn),
+    ..Default::default()
+  };
+  let diagnostics = crate::run_optimizer_with_diagnostics(code, options.clone());
+  assert_eq!(
+    diagnostics,
+    vec![
+      "warning: `tittle` is not a prop of `Card` (help: did you mean `title`?)",
+      "warning: `titel` is not a prop of `Card` (help: did you mean `title`?)",
+      "warning: `footer` is not a prop of `Card` (note: `Card` declares `body`, `title`)",
+    ]
+  );
+  let diagnostics = crate::run_optimizer_with_diagnostics(
+    code,
+    crate::Options {
+      lint: Some(LintLevel::Error),
+      ..options
+    },
+  );
+  assert!(diagnostics[0].starts_with("error: `tittle` is not a prop of `Card`"));
+}
+
+#[test]
+fn test_unused_props() {
+  let diagnostics = crate::run_optimizer_with_diagnostics(
+    r#"
+(function () {
+  var m = mango;
+  var Button = function (props) {
+    "use mango_component";
+    "use 5";
+    var label = props.label;
+    var icon = props.icon;
+    var size = props.size || "md";
+    var _u = props.size !== undefined;
+    var onTap = props.onTap;
+    return m.j("button", void 0, [label, icon, size, onTap]);
+  };
+  var Icon = function (props) {
+    "use mango_component";
+    "use 1";
+    var name = props.name;
+    return m.j("i", void 0, [name]);
+  };
+  m.o(document.body, [Button({ label: m.a("Ok") }), Icon({ name: m.a("x") })]);
+})();
+"#,
+    crate::Options {
+      lint: Some(LintLevel::Warn),
+      ..Default::default()
+    },
+  );
+  assert_eq!(
+    diagnostics,
+    vec![
+      "warning: `Button` declares props that no caller passes and that have no default: `icon`, `onTap` \
+       (note: `icon` is always undefined) (note: `onTap` is always undefined)"
+    ]
+  );
+}
@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::lazy::{lazy_component_bundle, lazy_component_call};
+use crate::{exports, prop_value_deps, Bailout, ComponentProp};
+
+/// How the components a bundle exports or imports are used by it, keyed by `<module>#<export>`.
+/// Manifests of every bundle are merged so that a second pass can fold props of shared components.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Manifest {
+  pub components: BTreeMap<String, ComponentUsage>,
+  /// Modules imported as a namespace or re-exported wholesale, whose exports may be used in any way.
+  pub escaped_modules: BTreeSet<String>,
+  /// Set when a lazy component is loaded from a computed URL, so any lazily loaded bundle may be passed anything.
+  pub dynamic_lazy_components: bool,
+  /// Build the manifest was emitted by. Manifests of other builds are stale and ignored.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub build_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ComponentUsage {
+  /// Set by the bundle that defines the component, when its props can be optimized.
+  pub is_defined: bool,
+  /// Set when the component is referenced other than by calling it, or its definition bailed out.
+  pub escapes: bool,
+  pub props: BTreeMap<String, PropUsage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PropUsage {
+  pub is_used: bool,
+  /// Every call site passes a value that no dynamic state flows into.
+  pub is_constant: bool,
+}
+
+impl Default for PropUsage {
+  fn default() -> Self {
+    Self {
+      is_used: false,
+      is_constant: true,
+    }
+  }
+}
+
+impl ComponentUsage {
+  fn merge(&mut self, other: &ComponentUsage) {
+    self.is_defined |= other.is_defined;
+    self.escapes |= other.escapes;
+    for (name, prop) in &other.props {
+      let merged = self.props.entry(name.clone()).or_default();
+      merged.is_used |= prop.is_used;
+      merged.is_constant &= prop.is_constant;
+    }
+  }
+}
+
+impl Manifest {
+  pub fn merge(&mut self, other: &Manifest) {
+    for (key, usage) in &other.components {
+      self.components.entry(key.clone()).or_default().merge(usage);
+    }
+    self.escaped_modules.extend(other.escaped_modules.iter().cloned());
+    self.dynamic_lazy_components |= other.dynamic_lazy_components;
+  }
+  /// Usage of a component across the whole app, unless its module escapes.
+  fn usage(&self, module: &str, key: &str) -> Option<&ComponentUsage> {
+    if self.escaped_modules.contains(module) {
+      return None;
+    }
+    self.components.get(key)
+  }
+}
+
+fn component_key(module: &str, name: &str) -> String {
+  format!("{}#{}", module, name)
+}
+
+/// Resolves an import specifier against the name of the importing bundle, e.g. `./b.js` from `dist/a.js` to `dist/b.js`.
+fn resolve(module: &str, src: &str) -> String {
+  if !src.starts_with("./") && !src.starts_with("../") {
+    return src.trim_start_matches('/').to_string();
+  }
+  let mut segments: Vec<&str> = module.split('/').collect();
+  segments.pop();
+  for segment in src.split('/') {
+    match segment {
+      "." | "" => {}
+      ".." => {
+        segments.pop();
+      }
+      segment => segments.push(segment),
+    }
+  }
+  segments.join("/")
+}
+
+/// Exported and imported bindings of a module, keyed the way manifests key components.
+/// The component a lazily loaded bundle returns is its default export, and the parameter of lazy component
+/// initializers is an import of the bundle they load.
+#[derive(Default)]
+pub(crate) struct ModuleBindings {
+  module: String,
+  pub(crate) exports: HashMap<Id, (String, String)>,
+  pub(crate) imports: HashMap<Id, (String, String)>,
+  lazy_component: Option<Id>,
+  escaped_modules: BTreeSet<String>,
+  escaped_keys: BTreeSet<String>,
+}
+
+impl ModuleBindings {
+  pub(crate) fn collect(program: &Program, module: &str) -> Self {
+    let mut bindings = Self {
+      module: module.to_string(),
+      ..Default::default()
+    };
+    program.visit_with(&mut bindings);
+    let program = match program {
+      Program::Module(program) => program,
+      Program::Script(script) => {
+        if let Some((_, component)) = script.body.iter().find_map(lazy_component_bundle) {
+          bindings.exports.insert(
+            component.to_id(),
+            (module.to_string(), component_key(module, "default")),
+          );
+          bindings.lazy_component = Some(component.to_id());
+        }
+        return bindings;
+      }
+    };
+    for decl in program.body.iter().filter_map(|item| item.as_module_decl()) {
+      for (local, name) in exports(decl) {
+        bindings
+          .exports
+          .insert(local.to_id(), (module.to_string(), component_key(module, &name)));
+      }
+      match decl {
+        ModuleDecl::Import(ImportDecl { specifiers, src, .. }) => {
+          let src = resolve(module, &src.value);
+          for specifier in specifiers {
+            let (local, name) = match specifier {
+              ImportSpecifier::Named(ImportNamedSpecifier { local, imported, .. }) => {
+                (local, imported.as_ref().map_or(local.sym.clone(), crate::export_name))
+              }
+              ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => (local, "default".into()),
+              ImportSpecifier::Namespace(_) => {
+                bindings.escaped_modules.insert(src.clone());
+                continue;
+              }
+            };
+            bindings
+              .imports
+              .insert(local.to_id(), (src.clone(), component_key(&src, &name)));
+          }
+        }
+        ModuleDecl::ExportNamed(NamedExport {
+          specifiers,
+          src: Some(src),
+          ..
+        }) => {
+          let src = resolve(module, &src.value);
+          for specifier in specifiers {
+            match specifier {
+              ExportSpecifier::Named(ExportNamedSpecifier { orig, .. }) => {
+                bindings
+                  .escaped_keys
+                  .insert(component_key(&src, &crate::export_name(orig)));
+              }
+              _ => {
+                bindings.escaped_modules.insert(src.clone());
+              }
+            }
+          }
+        }
+        ModuleDecl::ExportAll(ExportAll { src, .. }) => {
+          bindings.escaped_modules.insert(resolve(module, &src.value));
+        }
+        _ => {}
+      }
+    }
+    bindings
+  }
+  /// Usage of the components this module exports or imports, as recorded by the merged manifest.
+  /// An export missing from the manifest escapes, as some bundle may use it without having been analyzed.
+  pub(crate) fn usages(&self, manifest: &Manifest) -> HashMap<Id, ComponentUsage> {
+    let mut usages: HashMap<Id, ComponentUsage> = Default::default();
+    for (id, (module, key)) in &self.exports {
+      let usage = usages.entry(id.clone()).or_default();
+      let is_lazy = self.lazy_component.as_ref() == Some(id);
+      match manifest.usage(module, key) {
+        Some(other) if !(is_lazy && manifest.dynamic_lazy_components) => usage.merge(other),
+        _ => usage.escapes = true,
+      }
+    }
+    for (id, (module, key)) in &self.imports {
+      if let Some(other) = manifest.usage(module, key) {
+        usages.entry(id.clone()).or_default().merge(other);
+      }
+    }
+    usages
+  }
+}
+
+impl Visit for ModuleBindings {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((Some(src), Some(component))) = lazy_component_call(n) {
+      let src = resolve(&self.module, src);
+      let key = component_key(&src, "default");
+      self.imports.insert(component.to_id(), (src, key));
+    }
+    n.visit_children_with(self);
+  }
+}
+
+/// Records how this bundle defines and calls the components it exports or imports.
+pub(crate) struct ManifestCollector<'a> {
+  module: &'a str,
+  const_props: &'a HashSet<Id>,
+  stable_states: &'a HashSet<Id>,
+  tracked: HashMap<Id, String>,
+  manifest: Manifest,
+}
+
+impl<'a> ManifestCollector<'a> {
+  pub(crate) fn new(module: &'a str, const_props: &'a HashSet<Id>, stable_states: &'a HashSet<Id>) -> Self {
+    Self {
+      module,
+      const_props,
+      stable_states,
+      tracked: Default::default(),
+      manifest: Default::default(),
+    }
+  }
+  pub(crate) fn collect(
+    mut self,
+    program: &Program,
+    components: &HashMap<Id, HashMap<Id, ComponentProp>>,
+    bailouts: &HashMap<Id, Bailout>,
+  ) -> Manifest {
+    let bindings = ModuleBindings::collect(program, self.module);
+    for (id, (_, key)) in &bindings.exports {
+      let Some(props) = components.get(id) else {
+        continue;
+      };
+      let usage = self.manifest.components.entry(key.clone()).or_default();
+      let bailed_out = matches!(bailouts.get(id), Some(bailout) if !matches!(bailout, Bailout::Exported { .. }));
+      usage.is_defined = !bailed_out;
+      usage.escapes |= bailed_out;
+      for (name, _) in props.keys() {
+        usage.props.entry(name.to_string()).or_default();
+      }
+    }
+    for key in &bindings.escaped_keys {
+      self.manifest.components.entry(key.clone()).or_default().escapes = true;
+    }
+    self.manifest.escaped_modules = bindings.escaped_modules;
+    self.tracked = bindings
+      .exports
+      .into_iter()
+      .chain(bindings.imports)
+      .map(|(id, (_, key))| (id, key))
+      .collect();
+    program.visit_with(&mut self);
+    self.manifest
+  }
+  fn is_constant(&self, value: &Expr) -> bool {
+    prop_value_deps(value)
+      .iter()
+      .all(|dep| self.const_props.contains(dep) || self.stable_states.contains(dep))
+  }
+  fn record_call(&mut self, key: String, n: &CallExpr) {
+    let mut props: Vec<(String, bool)> = vec![];
+    let mut escapes = n.args.len() > 1;
+    match n.args.first() {
+      Some(ExprOrSpread { expr, spread: None }) => match &**expr {
+        Expr::Object(object) => {
+          for prop in &object.props {
+            match prop.as_prop().map(|prop| &**prop) {
+              Some(Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(name),
+                value,
+              })) => props.push((name.sym.to_string(), self.is_constant(value))),
+              Some(Prop::Shorthand(ident)) => {
+                props.push((ident.sym.to_string(), self.is_constant(&Expr::Ident(ident.clone()))))
+              }
+              _ => escapes = true,
+            }
+          }
+        }
+        _ => escapes = true,
+      },
+      Some(_) => escapes = true,
+      None => {}
+    }
+    let usage = self.manifest.components.entry(key).or_default();
+    usage.escapes |= escapes;
+    for (name, is_constant) in props {
+      let prop = usage.props.entry(name).or_default();
+      prop.is_used = true;
+      prop.is_constant &= is_constant;
+    }
+  }
+}
+
+impl Visit for ManifestCollector<'_> {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    match lazy_component_call(n) {
+      Some((Some(src), None)) => {
+        let key = component_key(&resolve(self.module, src), "default");
+        self.manifest.components.entry(key).or_default().escapes = true;
+      }
+      Some((None, _)) => self.manifest.dynamic_lazy_components = true,
+      _ => {}
+    }
+    match &n.callee {
+      Callee::Expr(callee) => match &**callee {
+        Expr::Ident(callee) if self.tracked.contains_key(&callee.to_id()) => {
+          let key = self.tracked[&callee.to_id()].clone();
+          self.record_call(key, n);
+          n.args.visit_with(self);
+          return;
+        }
+        _ => {}
+      },
+      Callee::Import(_) => {
+        if let Some(Expr::Lit(Lit::Str(src))) = n.args.first().map(|arg| &*arg.expr) {
+          self.manifest.escaped_modules.insert(resolve(self.module, &src.value));
+        }
+      }
+      Callee::Super(_) => {}
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_expr(&mut self, n: &Expr) {
+    if let Some(key) = n.as_ident().and_then(|ident| self.tracked.get(&ident.to_id())) {
+      self.manifest.components.entry(key.clone()).or_default().escapes = true;
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_module_decl(&mut self, n: &ModuleDecl) {
+    if !matches!(n, ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) if expr.is_ident()) {
+      n.visit_children_with(self);
+    }
+  }
+  fn visit_script(&mut self, n: &Script) {
+    for stmt in &n.body {
+      match lazy_component_bundle(stmt) {
+        Some((stmts, _)) => stmts.visit_with(self),
+        None => stmt.visit_with(self),
+      }
+    }
+  }
+}
+
+#[test]
+fn test_manifest() {
+  assert_eq!(resolve("dist/pages/a.js", "./b.js"), "dist/pages/b.js");
+  assert_eq!(resolve("dist/pages/a.js", "../shared/c.js"), "dist/shared/c.js");
+  assert_eq!(resolve("a.js", "/shared.js"), "shared.js");
+  assert_eq!(resolve("a.js", "@mango-js/runtime"), "@mango-js/runtime");
+
+  let shared = r#"
+import * as Mango from "@mango-js/runtime";
+export function Card(props) {
+  "use mango_component";
+  "use 2";
+  var title = props.title;
+  var tone = props.tone;
+  return Mango.j("h2", void 0, [Mango.i(function () { return Mango.b(title) + Mango.b(tone); }, /* DYNAMIC_VIEW_DEPS */[title, tone])]);
+}
+"#;
+  let page = r#"
+import * as Mango from "@mango-js/runtime";
+import { Card as C } from "./shared.js";
+var count = Mango.a(0);
+Mango.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { Mango.c(count, Mango.b(count) + 1); }; }]]);
+Mango.o(document.body, [C({ title: Mango.a("Hi"), tone: count })]);
+"#;
+  let emit = |code: &str, module: &str, manifests: Vec<Manifest>| {
+    crate::run_module_optimizer(
+      code,
+      crate::Options {
+        module_name: Some(module.into()),
+        emit_manifest: true,
+        manifests,
+        ..Default::default()
+      },
+    )
+  };
+  let (_, shared_report) = emit(shared, "shared.js", vec![]);
+  let (_, page_report) = emit(page, "page.js", vec![]);
+  let shared_manifest = shared_report.manifest.unwrap();
+  let page_manifest = page_report.manifest.unwrap();
+  assert!(shared_manifest.components["shared.js#Card"].is_defined);
+  assert_eq!(
+    page_manifest.components["shared.js#Card"].props["tone"],
+    PropUsage {
+      is_used: true,
+      is_constant: false
+    }
+  );
+
+  let (shared_output, _) = emit(
+    shared,
+    "shared.js",
+    vec![shared_manifest.clone(), page_manifest.clone()],
+  );
+  let (page_output, _) = emit(page, "page.js", vec![shared_manifest.clone(), page_manifest.clone()]);
+  assert!(shared_output.contains("return title+Mango.b(tone);},[tone])"));
+  assert!(page_output.contains(r#"C({title:"Hi",tone:count})"#));
+
+  let stale = |manifest: &Manifest| Manifest {
+    build_id: Some("previous".into()),
+    ..manifest.clone()
+  };
+  let (shared_output, _) = emit(
+    shared,
+    "shared.js",
+    vec![stale(&shared_manifest), stale(&page_manifest)],
+  );
+  assert!(shared_output.contains("Mango.b(title)+Mango.b(tone)"));
+
+  let mut escaping = page_manifest;
+  escaping.escaped_modules.insert("shared.js".into());
+  let (shared_output, _) = emit(shared, "shared.js", vec![escaping]);
+  assert!(shared_output.contains("Mango.b(title)+Mango.b(tone)"));
+}
@@ -0,0 +1,473 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use swc_common::comments::{Comments, SingleThreadedComments};
+use swc_common::errors::{self, DiagnosticBuilder, Handler, HANDLER};
+use swc_common::{FileName, Mark, SourceMap, Span, Spanned, GLOBALS};
+use swc_ecma_ast::*;
+use swc_ecma_codegen::text_writer::JsWriter;
+use swc_ecma_codegen::Emitter;
+use swc_ecma_minifier::optimize;
+use swc_ecma_minifier::option::{CompressOptions, ExtraOptions, MangleOptions, MinifyOptions};
+use swc_ecma_parser::{parse_file_as_module, parse_file_as_script, Syntax};
+use swc_ecma_preset_env::{preset_env, Targets};
+use swc_ecma_transforms::fixer::fixer;
+use swc_ecma_transforms::helpers::{inject_helpers, Helpers, HELPERS};
+use swc_ecma_transforms::resolver;
+use swc_ecma_visit::*;
+
+use crate::translations::{TranslationCall, TranslationCollector};
+use crate::{optimize as optimize_components, Options, Report};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PipelineOptions {
+  pub filename: Option<String>,
+  pub source_map: bool,
+  pub input_source_map: Option<String>,
+  pub passes: usize,
+  pub ie8: bool,
+  /// Works around Safari 10 bugs when mangling, e.g. with `let` in loops.
+  pub safari10: bool,
+  /// Browsers to transpile the bundle for, e.g. a browserslist query. It is left as is when omitted.
+  pub targets: Option<Targets>,
+  /// Parses the bundle as an ES module rather than a script.
+  pub module: bool,
+  /// Lists the `MANGO_TRANSLATION` calls of the bundle in `PipelineOutput::translations`.
+  /// The calls are left in place rather than inlined, so that those removed as dead code can be told apart.
+  pub extract_translations: bool,
+  pub optimizer: Options,
+}
+
+impl Default for PipelineOptions {
+  fn default() -> Self {
+    Self {
+      filename: None,
+      source_map: false,
+      input_source_map: None,
+      passes: 2,
+      ie8: true,
+      safari10: true,
+      targets: None,
+      module: false,
+      extract_translations: false,
+      optimizer: Default::default(),
+    }
+  }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PipelineOutput {
+  pub code: String,
+  pub map: Option<String>,
+  pub report: Report,
+  pub warnings: Vec<PipelineWarning>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub translations: Option<Vec<TranslationCall>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PipelineWarning {
+  pub message: String,
+  pub line: usize,
+  pub column: usize,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub notes: Vec<PipelineWarning>,
+}
+
+struct DiagnosticCollector {
+  cm: Arc<SourceMap>,
+  warnings: Arc<Mutex<Vec<PipelineWarning>>>,
+  errors: Arc<Mutex<Vec<PipelineError>>>,
+}
+
+impl DiagnosticCollector {
+  fn warning(&self, message: String, span: Option<Span>) -> PipelineWarning {
+    let (line, column) = match span.filter(|span| !span.is_dummy()) {
+      Some(span) => {
+        let loc = self.cm.lookup_char_pos(span.lo);
+        (loc.line, loc.col.0 + 1)
+      }
+      None => (0, 0),
+    };
+    PipelineWarning {
+      message,
+      line,
+      column,
+      notes: vec![],
+    }
+  }
+}
+
+impl errors::Emitter for DiagnosticCollector {
+  fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+    let mut warning = self.warning(db.message(), db.span.primary_span());
+    if db.is_error() {
+      let mut message = warning.message;
+      for child in &db.children {
+        message.push_str(&format!("\n{}: {}", child.level, child.message()));
+      }
+      self.errors.lock().unwrap().push(PipelineError {
+        message,
+        line: warning.line,
+        column: warning.column,
+      });
+      return;
+    }
+    warning.notes = db
+      .children
+      .iter()
+      .map(|child| self.warning(child.message(), child.span.primary_span()))
+      .collect();
+    self.warnings.lock().unwrap().push(warning);
+  }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PipelineError {
+  pub message: String,
+  pub line: usize,
+  pub column: usize,
+}
+
+impl fmt::Display for PipelineError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} ({}:{})", self.message, self.line, self.column)
+  }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// Minifies a bundle, runs the optimizer on it, then minifies the result again.
+pub fn run_pipeline(code: &str, options: &PipelineOptions) -> Result<PipelineOutput, PipelineError> {
+  let cm: Arc<SourceMap> = Default::default();
+  let filename = match &options.filename {
+    Some(filename) => FileName::Custom(filename.clone()),
+    None => FileName::Anon,
+  };
+  let fm = cm.new_source_file(filename.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let error_at = |message: String, span: Span| {
+    let loc = cm.lookup_char_pos(span.lo);
+    PipelineError {
+      message,
+      line: loc.line,
+      column: loc.col.0 + 1,
+    }
+  };
+  let input_source_map = match &options.input_source_map {
+    Some(map) => Some(
+      sourcemap::SourceMap::from_slice(map.as_bytes()).map_err(|err| PipelineError {
+        message: format!("invalid input source map: {}", err),
+        line: 0,
+        column: 0,
+      })?,
+    ),
+    None => None,
+  };
+  let targets = match &options.targets {
+    Some(targets) => Some(
+      preset_env_base::query::targets_to_versions(Some(targets.clone())).map_err(|err| PipelineError {
+        message: format!("invalid targets: {:#}", err),
+        line: 0,
+        column: 0,
+      })?,
+    ),
+    None => None,
+  };
+  let mut errors = vec![];
+  let program = if options.module {
+    parse_file_as_module(
+      &fm,
+      Syntax::Es(Default::default()),
+      EsVersion::latest(),
+      Some(&comments),
+      &mut errors,
+    )
+    .map(Program::Module)
+  } else {
+    parse_file_as_script(
+      &fm,
+      Syntax::Es(Default::default()),
+      EsVersion::latest(),
+      Some(&comments),
+      &mut errors,
+    )
+    .map(Program::Script)
+  }
+  .map_err(|err| error_at(err.kind().msg().to_string(), err.span()))?;
+  if let Some(err) = errors.first() {
+    return Err(error_at(err.kind().msg().to_string(), err.span()));
+  }
+  let optimizer_options = if options.extract_translations {
+    Cow::Owned(Options {
+      dictionary: None,
+      ..options.optimizer.clone()
+    })
+  } else {
+    Cow::Borrowed(&options.optimizer)
+  };
+
+  GLOBALS.set(&Default::default(), || {
+    let unresolved_mark = Mark::new();
+    let top_level_mark = Mark::new();
+    // Mangling is left to the final pass, as it reuses names across scopes, which the optimizer can't tell apart.
+    // `safari10` is deprecated as the mangler always works around Safari 10 now, but is still set like the fallback.
+    #[allow(deprecated)]
+    let minify_options = |is_final_pass: bool| MinifyOptions {
+      rename: false,
+      compress: Some(CompressOptions {
+        passes: options.passes,
+        keep_fargs: false,
+        negate_iife: false,
+        side_effects: true,
+        props: false,
+        directives: is_final_pass,
+        ie8: options.ie8,
+        module: options.module,
+        ..Default::default()
+      }),
+      mangle: is_final_pass.then(|| MangleOptions {
+        ie8: options.ie8,
+        safari10: options.safari10,
+        ..Default::default()
+      }),
+      wrap: false,
+      enclose: false,
+    };
+    let extra_options = ExtraOptions {
+      unresolved_mark,
+      top_level_mark,
+      mangle_name_cache: Default::default(),
+    };
+
+    let mut program = program.apply(&mut resolver(unresolved_mark, top_level_mark, false));
+    if let Some(targets) = targets {
+      // Helpers are inlined, as the bundle is final.
+      program = HELPERS.set(&Helpers::new(false), || {
+        program
+          .apply(&mut preset_env(
+            unresolved_mark,
+            Some(&comments),
+            swc_ecma_preset_env::Config {
+              targets: Some(Targets::Versions(targets)),
+              ..Default::default()
+            },
+            Default::default(),
+            &mut Default::default(),
+          ))
+          .apply(&mut inject_helpers(unresolved_mark))
+      });
+    }
+    let translation_calls = options.extract_translations.then(|| {
+      let mut collector = TranslationCollector::new(&program);
+      program.visit_with(&mut collector);
+      collector.calls
+    });
+    let mut program = optimize(
+      program,
+      cm.clone(),
+      Some(&comments),
+      None,
+      &minify_options(false),
+      &extra_options,
+    );
+    let warnings: Arc<Mutex<Vec<PipelineWarning>>> = Default::default();
+    let errors: Arc<Mutex<Vec<PipelineError>>> = Default::default();
+    let handler = Handler::with_emitter(
+      true,
+      false,
+      Box::new(DiagnosticCollector {
+        cm: cm.clone(),
+        warnings: warnings.clone(),
+        errors: errors.clone(),
+      }),
+    );
+    let mut report = HANDLER.set(&handler, || {
+      optimize_components(&mut program, &comments, &optimizer_options)
+    });
+    report.locate(&cm);
+    if let Some(error) = errors.lock().unwrap().drain(..).next() {
+      return Err(error);
+    }
+    let program = optimize(program, cm.clone(), None, None, &minify_options(true), &extra_options);
+    let translations = translation_calls.map(|calls| {
+      let mut collector = TranslationCollector::new(&program);
+      program.visit_with(&mut collector);
+      let live: HashSet<_> = collector.calls.into_iter().map(|(span, ..)| span.lo).collect();
+      calls
+        .into_iter()
+        .map(|(span, id, params)| {
+          let loc = cm.lookup_char_pos(span.lo);
+          let original = input_source_map
+            .as_ref()
+            .and_then(|map| map.lookup_token(loc.line as u32 - 1, loc.col.0 as u32))
+            .filter(|token| token.has_source());
+          let (source, line, column) = match original {
+            Some(token) => (
+              token.get_source().map(str::to_string),
+              token.get_src_line() as usize + 1,
+              token.get_src_col() as usize + 1,
+            ),
+            None => (options.filename.clone(), loc.line, loc.col.0 + 1),
+          };
+          TranslationCall {
+            id,
+            source,
+            line,
+            column,
+            params,
+            is_live: live.contains(&span.lo),
+          }
+        })
+        .collect()
+    });
+    let program = program.apply(&mut fixer(Some(&comments as &dyn Comments)));
+
+    let mut src = vec![];
+    let mut src_map = vec![];
+    let mut emitter = Emitter {
+      cfg: swc_ecma_codegen::Config::default().with_minify(true),
+      comments: None,
+      cm: cm.clone(),
+      wr: Box::new(JsWriter::new(
+        cm.clone(),
+        "\n",
+        &mut src,
+        options.source_map.then_some(&mut src_map),
+      )),
+    };
+    emitter
+      .emit_program(&program)
+      .map_err(|err| error_at(err.to_string(), program.span()))?;
+    drop(emitter);
+
+    let map = if options.source_map {
+      let mut map = vec![];
+      cm.build_source_map_from(&src_map, input_source_map.as_ref())
+        .to_writer(&mut map)
+        .map_err(|err| error_at(err.to_string(), program.span()))?;
+      Some(String::from_utf8(map).unwrap())
+    } else {
+      None
+    };
+    let warnings = std::mem::take(&mut *warnings.lock().unwrap());
+    Ok(PipelineOutput {
+      code: String::from_utf8(src).unwrap(),
+      map,
+      report,
+      warnings,
+      translations,
+    })
+  })
+}
+
+#[test]
+fn test_pipeline() {
+  let code = r#"
+(function () {
+  var Mango = mango;
+  var Greeting = function (props) {
+    "use mango_component";
+    "use 1";
+    var name = props.name;
+    return Mango.j("h1", void 0, [Mango.i(function () { return Mango.b(name); }, /* DYNAMIC_VIEW_DEPS */[name])]);
+  };
+  Mango.o(document.body, [Greeting({ name: Mango.a("World") }), Greeting({ name: Mango.a("Mango") })]);
+})();
+"#;
+  let output = run_pipeline(
+    code,
+    &PipelineOptions {
+      filename: Some("index.js".into()),
+      source_map: true,
+      ..Default::default()
+    },
+  )
+  .unwrap();
+  assert!(!output.code.contains(".i("));
+  assert!(output.code.contains(r#"({name:"World"})"#));
+  let map = output.map.unwrap();
+  assert!(map.contains(r#""sources":["index.js"]"#));
+
+  let output = run_pipeline(
+    code,
+    &PipelineOptions {
+      filename: Some("index.js".into()),
+      source_map: true,
+      input_source_map: Some(r#"{"version":3,"sources":["app.jsx"],"names":[],"mappings":";AAAA;AACA;AACA"}"#.into()),
+      ..Default::default()
+    },
+  )
+  .unwrap();
+  assert!(output.map.unwrap().contains(r#""sources":["app.jsx"]"#));
+  assert_eq!(output.report.rounds, 1);
+
+  let output = run_pipeline(
+    r#"
+import * as Mango from "@mango-js/runtime";
+function Greeting(props) {
+  "use mango_component";
+  "use 1";
+  var name = props.name;
+  return Mango.j("h1", void 0, [Mango.i(function () { return Mango.b(name); }, /* DYNAMIC_VIEW_DEPS */[name])]);
+}
+export function Page(props) {
+  "use mango_component";
+  "use 1";
+  var title = props.title;
+  return Mango.j("main", void 0, [Mango.i(function () { return Mango.b(title); }, /* DYNAMIC_VIEW_DEPS */[title]), Greeting({ name: Mango.a("World") }), Greeting({ name: Mango.a("Mango") })]);
+}
+"#,
+    &PipelineOptions {
+      module: true,
+      ..Default::default()
+    },
+  )
+  .unwrap();
+  assert!(output.code.contains(r#"({name:"World"})"#));
+  assert!(output.code.contains(".i(function(){return"));
+  assert!(output.code.contains("export function Page("));
+
+  let output = run_pipeline(
+    r#"
+(function () {
+  var debug = false;
+  document.title = MANGO_TRANSLATION("title", { name: "Mango", "count": 1 });
+  if (debug) console.log(MANGO_TRANSLATION("debug", undefined));
+})();
+"#,
+    &PipelineOptions {
+      filename: Some("index.js".into()),
+      extract_translations: true,
+      input_source_map: Some(r#"{"version":3,"sources":["app.jsx"],"names":[],"mappings":";;;AAEA;AACA"}"#.into()),
+      ..Default::default()
+    },
+  )
+  .unwrap();
+  assert!(output.code.contains(r#"MANGO_TRANSLATION("title""#));
+  assert_eq!(
+    serde_json::to_value(output.translations.unwrap()).unwrap(),
+    serde_json::json!([
+      { "id": "title", "source": "app.jsx", "line": 3, "column": 1, "params": ["name", "count"], "isLive": true },
+      { "id": "debug", "source": "app.jsx", "line": 4, "column": 1, "params": [], "isLive": false },
+    ])
+  );
+
+  let targeted = |targets: serde_json::Value| {
+    run_pipeline(
+      &code.replace("function () { return Mango.b(name); }", "() => Mango.b(name)"),
+      &serde_json::from_value(serde_json::json!({ "targets": targets })).unwrap(),
+    )
+  };
+  let output = targeted(serde_json::json!(["ie 11"])).unwrap();
+  assert!(!output.code.contains("=>"));
+  assert!(output.code.contains(r#"({name:"World"})"#));
+  assert!(targeted(serde_json::json!("chrome 120")).unwrap().code.contains("=>"));
+  assert!(targeted(serde_json::json!("unknown 1")).is_err());
+
+  let err = run_pipeline("var a = ;", &Default::default()).unwrap_err();
+  assert_eq!((err.line, err.column), (1, 9));
+}
@@ -0,0 +1,461 @@
+use std::collections::HashSet;
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::hoist::{is_void, static_element_size};
+use crate::runtime::{runtime_callee, CREATE_STATIC_ELEMENT};
+
+const VOID_TAGS: &[&str] = &[
+  "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+// Elements that can't be created by assigning `innerHTML` of a `div` consistently across browsers.
+const UNSAFE_TAGS: &[&str] = &[
+  "base",
+  "body",
+  "frame",
+  "frameset",
+  "head",
+  "html",
+  "iframe",
+  "link",
+  "math",
+  "meta",
+  "noembed",
+  "noframes",
+  "noscript",
+  "plaintext",
+  "script",
+  "style",
+  "svg",
+  "template",
+  "title",
+  "xmp",
+];
+
+// Elements implicitly closing an open `p` element while parsing.
+const P_CLOSING_TAGS: &[&str] = &[
+  "address",
+  "article",
+  "aside",
+  "blockquote",
+  "details",
+  "dialog",
+  "div",
+  "dl",
+  "fieldset",
+  "figcaption",
+  "figure",
+  "footer",
+  "form",
+  "h1",
+  "h2",
+  "h3",
+  "h4",
+  "h5",
+  "h6",
+  "header",
+  "hgroup",
+  "hr",
+  "li",
+  "main",
+  "menu",
+  "nav",
+  "ol",
+  "p",
+  "pre",
+  "section",
+  "table",
+  "ul",
+];
+
+const HEADING_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+const BOOLEAN_PROPS: &[&str] = &["disabled", "hidden"];
+
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+pub(crate) struct PrerenderVisitor<'a> {
+  pub(crate) aliases: &'a HashSet<Id>,
+  pub(crate) threshold: usize,
+}
+
+impl VisitMut for PrerenderVisitor<'_> {
+  fn visit_mut_expr(&mut self, n: &mut Expr) {
+    if let Expr::Call(call_expr) = n {
+      if let Some(size) = static_element_size(call_expr, self.aliases) {
+        if size > self.threshold {
+          if let Some((alias, _)) = runtime_callee(call_expr) {
+            let mut serializer = HtmlSerializer {
+              aliases: self.aliases,
+              html: String::new(),
+              ancestors: vec![],
+            };
+            if let Some(tag) = element_tag(call_expr) {
+              let (open_wrappers, close_wrappers, depth) = wrap_for(tag);
+              serializer.html.push_str(open_wrappers);
+              if serializer.element(call_expr).is_some() {
+                serializer.html.push_str(close_wrappers);
+                let mut args = vec![Expr::Lit(Lit::Str(serializer.html.into())).into()];
+                if depth > 0 {
+                  args.push(Expr::Lit(Lit::Num(depth.into())).into());
+                }
+                *n = Expr::Call(CallExpr {
+                  callee: Callee::Expr(Box::new(Expr::Member(MemberExpr {
+                    obj: Box::new(Expr::Ident(alias.clone())),
+                    prop: MemberProp::Ident(IdentName::new(CREATE_STATIC_ELEMENT.into(), DUMMY_SP)),
+                    span: DUMMY_SP,
+                  }))),
+                  args,
+                  span: call_expr.span,
+                  ..Default::default()
+                });
+                return;
+              }
+            }
+          }
+        }
+      }
+    }
+    n.visit_mut_children_with(self);
+  }
+}
+
+struct HtmlSerializer<'a> {
+  aliases: &'a HashSet<Id>,
+  html: String,
+  ancestors: Vec<String>,
+}
+
+impl HtmlSerializer<'_> {
+  fn element(&mut self, n: &CallExpr) -> Option<()> {
+    let tag = element_tag(n)?.to_ascii_lowercase();
+    if !matches!(
+      n.args.get(3).map(|arg| &*arg.expr),
+      None | Some(Expr::Lit(Lit::Num(Number { value: 0.0, .. })))
+    ) || UNSAFE_TAGS.contains(&&*tag)
+      || !self.can_contain(&tag)
+    {
+      return None;
+    }
+    self.html.push('<');
+    self.html.push_str(&tag);
+    if let Some(ExprOrSpread { expr, .. }) = n.args.get(1) {
+      if let Expr::Array(ArrayLit { elems, .. }) = &**expr {
+        let mut attrs: Vec<(String, Option<String>)> = vec![];
+        let mut styles: Vec<(String, String)> = vec![];
+        for elem in elems.iter().flatten() {
+          let Expr::Array(ArrayLit { elems, .. }) = &*elem.expr else {
+            return None;
+          };
+          let Some(Some(ExprOrSpread { expr, .. })) = elems.first() else {
+            return None;
+          };
+          let Expr::Fn(FnExpr { function, .. }) = &**expr else {
+            return None;
+          };
+          for stmt in function.body.as_ref()?.stmts.iter() {
+            let Stmt::Expr(ExprStmt { expr, .. }) = stmt else {
+              return None;
+            };
+            let mutations = match &**expr {
+              Expr::Seq(SeqExpr { exprs, .. }) => exprs.iter().map(|expr| &**expr).collect(),
+              expr => vec![expr],
+            };
+            for mutation in mutations {
+              collect_attr(mutation, &mut attrs, &mut styles)?;
+            }
+          }
+        }
+        if !styles.is_empty() {
+          let style = styles
+            .iter()
+            .map(|(name, value)| format!("{}:{}", name, value))
+            .collect::<Vec<_>>()
+            .join(";");
+          set_attr(&mut attrs, "style".into(), Some(style));
+        }
+        for (name, value) in attrs {
+          self.html.push(' ');
+          self.html.push_str(&name);
+          if let Some(value) = value {
+            self.html.push_str("=\"");
+            self.html.push_str(&escape_attr(&value));
+            self.html.push('"');
+          }
+        }
+      }
+    }
+    self.html.push('>');
+    let children = match n.args.get(2).map(|arg| &*arg.expr) {
+      Some(Expr::Array(ArrayLit { elems, .. })) => Some(elems),
+      _ => None,
+    };
+    if VOID_TAGS.contains(&&*tag) {
+      return if children.is_none_or(|elems| elems.is_empty()) {
+        Some(())
+      } else {
+        None
+      };
+    }
+    self.ancestors.push(tag.clone());
+    let content_start = self.html.len();
+    if let Some(elems) = children {
+      self.children(elems)?;
+    }
+    if matches!(&*tag, "pre" | "textarea" | "listing") && self.html[content_start..].starts_with('\n') {
+      self.html.insert(content_start, '\n');
+    }
+    self.ancestors.pop();
+    self.html.push_str("</");
+    self.html.push_str(&tag);
+    self.html.push('>');
+    Some(())
+  }
+  fn children(&mut self, elems: &[Option<ExprOrSpread>]) -> Option<()> {
+    for elem in elems.iter().flatten() {
+      match &*elem.expr {
+        Expr::Array(ArrayLit { elems, .. }) => self.children(elems)?,
+        Expr::Call(call_expr) => {
+          static_element_size(call_expr, self.aliases)?;
+          self.element(call_expr)?;
+        }
+        Expr::Lit(Lit::Null(_)) => {}
+        expr if is_void(expr) || static_bool(expr) == Some(false) => {}
+        expr => {
+          let text = static_value_to_string(expr)?;
+          if text.is_empty() {
+            continue;
+          }
+          if !self.can_contain_text() {
+            return None;
+          }
+          self.html.push_str(&escape_text(&text));
+        }
+      }
+    }
+    Some(())
+  }
+  fn can_contain(&self, tag: &str) -> bool {
+    let parent = self.ancestors.last().map(|parent| &**parent);
+    if self.ancestors.iter().any(|ancestor| ancestor == "p") && P_CLOSING_TAGS.contains(&tag)
+      || self.ancestors.iter().any(|ancestor| ancestor == tag) && matches!(tag, "a" | "button" | "form" | "nobr")
+      || self
+        .ancestors
+        .iter()
+        .any(|ancestor| HEADING_TAGS.contains(&&**ancestor))
+        && HEADING_TAGS.contains(&tag)
+      || parent == Some(tag) && matches!(tag, "li" | "dd" | "dt" | "option" | "optgroup")
+    {
+      return false;
+    }
+    match parent {
+      None => true,
+      Some("table") => matches!(tag, "caption" | "colgroup" | "thead" | "tbody" | "tfoot"),
+      Some("thead" | "tbody" | "tfoot") => tag == "tr",
+      Some("tr") => matches!(tag, "td" | "th"),
+      Some("colgroup") => tag == "col",
+      Some("select") => matches!(tag, "option" | "optgroup"),
+      Some("optgroup") => tag == "option",
+      Some("option" | "textarea") => false,
+      Some(_) => !matches!(
+        tag,
+        "caption" | "col" | "colgroup" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th"
+      ),
+    }
+  }
+  fn can_contain_text(&self) -> bool {
+    !matches!(
+      self.ancestors.last().map(|parent| &**parent),
+      Some("table" | "thead" | "tbody" | "tfoot" | "tr" | "colgroup" | "select" | "optgroup")
+    )
+  }
+}
+
+fn element_tag(n: &CallExpr) -> Option<&str> {
+  if let Some(ExprOrSpread { expr, .. }) = n.args.first() {
+    if let Expr::Lit(Lit::Str(Str { value, .. })) = &**expr {
+      if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Some(value);
+      }
+    }
+  }
+  None
+}
+
+fn wrap_for(tag: &str) -> (&'static str, &'static str, usize) {
+  match &*tag.to_ascii_lowercase() {
+    "tr" => ("<table><tbody>", "</tbody></table>", 2),
+    "td" | "th" => ("<table><tbody><tr>", "</tr></tbody></table>", 3),
+    "thead" | "tbody" | "tfoot" | "caption" | "colgroup" => ("<table>", "</table>", 1),
+    "col" => ("<table><colgroup>", "</colgroup></table>", 2),
+    "option" | "optgroup" => ("<select multiple>", "</select>", 1),
+    "legend" => ("<fieldset>", "</fieldset>", 1),
+    "area" => ("<map>", "</map>", 1),
+    "param" => ("<object>", "</object>", 1),
+    _ => ("", "", 0),
+  }
+}
+
+fn collect_attr(n: &Expr, attrs: &mut Vec<(String, Option<String>)>, styles: &mut Vec<(String, String)>) -> Option<()> {
+  match n {
+    Expr::Assign(AssignExpr {
+      left:
+        AssignTarget::Simple(SimpleAssignTarget::Member(MemberExpr {
+          obj,
+          prop: MemberProp::Ident(prop),
+          ..
+        })),
+      right,
+      ..
+    }) => {
+      if let Expr::Member(_) = &**obj {
+        let value = static_value_to_string(right)?;
+        let name = style_name(&prop.sym);
+        styles.retain(|(style, _)| *style != name);
+        if !value.is_empty() {
+          styles.push((name, value));
+        }
+      } else if BOOLEAN_PROPS.contains(&&*prop.sym) {
+        if static_bool(right)? {
+          set_attr(attrs, prop.sym.to_string(), None);
+        } else {
+          attrs.retain(|(attr, _)| **attr != *prop.sym);
+        }
+      } else {
+        let value = static_value_to_string(right)?;
+        let name = match &*prop.sym {
+          "className" => "class".into(),
+          "htmlFor" => "for".into(),
+          name => name.to_ascii_lowercase(),
+        };
+        set_attr(attrs, name, Some(value));
+      }
+    }
+    Expr::Call(CallExpr { args, .. }) => {
+      let Expr::Lit(Lit::Str(Str { value: name, .. })) = &*args.first()?.expr else {
+        return None;
+      };
+      let value = static_value_to_string(&args.get(1)?.expr)?;
+      if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+      {
+        return None;
+      }
+      set_attr(attrs, name.to_lowercase(), Some(value));
+    }
+    _ => return None,
+  }
+  Some(())
+}
+
+fn set_attr(attrs: &mut Vec<(String, Option<String>)>, name: String, value: Option<String>) {
+  if let Some(attr) = attrs.iter_mut().find(|(attr, _)| *attr == name) {
+    attr.1 = value;
+  } else {
+    attrs.push((name, value));
+  }
+}
+
+fn style_name(prop: &str) -> String {
+  if prop == "cssFloat" || prop == "styleFloat" {
+    return "float".into();
+  }
+  let mut name = String::with_capacity(prop.len() + 4);
+  if prop.starts_with("ms") && prop[2..].starts_with(|c: char| c.is_ascii_uppercase()) {
+    name.push('-');
+  }
+  for (idx, c) in prop.char_indices() {
+    if c.is_ascii_uppercase() {
+      if idx > 0 || prop.starts_with("Webkit") || prop.starts_with("Moz") || prop.starts_with("O") {
+        name.push('-');
+      }
+      name.push(c.to_ascii_lowercase());
+    } else {
+      name.push(c);
+    }
+  }
+  name
+}
+
+fn static_bool(n: &Expr) -> Option<bool> {
+  match n {
+    Expr::Lit(Lit::Bool(Bool { value, .. })) => Some(*value),
+    Expr::Unary(UnaryExpr { op: op!("!"), arg, .. }) => match &**arg {
+      Expr::Lit(Lit::Num(Number { value, .. })) if *value == 0.0 || *value == 1.0 => Some(*value == 0.0),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn static_value_to_string(n: &Expr) -> Option<String> {
+  match n {
+    Expr::Lit(Lit::Str(Str { value, .. })) => Some(value.to_string()),
+    // Only safe integers print the same in Rust and JS.
+    Expr::Lit(Lit::Num(Number { value, .. })) if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER => {
+      Some((*value as i64).to_string())
+    }
+    _ => static_bool(n).map(|value| value.to_string()),
+  }
+}
+
+fn escape_text(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_attr(value: &str) -> String {
+  value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+#[test]
+fn test_prerender() {
+  let output = crate::run_optimizer_with_config(
+    r##"
+(function () {
+  var m = mango;
+  var Legal = function () {
+    return m.j("footer", [/* DYNAMIC_ATTRS */[function (i) { i.className = "legal", i.style.backgroundColor = "#fff"; }]], [
+      m.j("p", void 0, ["Terms & \"conditions\" <apply>", m.j("br"), "Read ", m.j("a", [/* DYNAMIC_ATTRS */[function (i) { i.href = "/terms?a=1&b=2"; }]], ["more"])]),
+      m.j("pre", void 0, ["\nindented"])
+    ]);
+  };
+  var Row = function () {
+    return m.j("tr", void 0, [m.j("td", void 0, ["a"]), m.j("td", void 0, ["b"]), m.j("td", void 0, ["c"])]);
+  };
+  var Invalid = function () {
+    return m.j("p", void 0, [m.j("div", void 0, ["block"]), m.j("span", void 0, ["inline"]), "text"]);
+  };
+  var Small = function () {
+    return m.j("span", void 0, ["tiny"]);
+  };
+  var Flags = function () {
+    return m.j("div", [/* DYNAMIC_ATTRS */[function (i) { i.hidden = !1; i.title = 42; }]], ["false", !1, 7, m.j("b", void 0, ["x"]), m.j("b")]);
+  };
+  var Hidden = function () {
+    return m.j("section", [/* DYNAMIC_ATTRS */[function (i) { i.hidden = "false"; }]], [m.j("i", void 0, ["a"]), m.j("i", void 0, ["b"])]);
+  };
+  var Fraction = function () {
+    return m.j("label", void 0, [0.1, m.j("i", void 0, ["a"]), m.j("i", void 0, ["b"])]);
+  };
+  m.o(document.body, [Legal(), Row(), Invalid(), Small(), Flags(), Hidden(), Fraction()]);
+})();
+"##,
+    crate::Options {
+      prerender_threshold: Some(3),
+      ..Default::default()
+    },
+  );
+  assert!(output.contains(
+    r#"m.q('<footer class="legal" style="background-color:#fff"><p>Terms &amp; "conditions" &lt;apply&gt;<br>Read <a href="/terms?a=1&amp;b=2">more</a></p><pre>\n\nindented</pre></footer>')"#
+  ));
+  assert!(output.contains(r#"m.q("<table><tbody><tr><td>a</td><td>b</td><td>c</td></tr></tbody></table>",2)"#));
+  assert!(output.contains(r#"m.j("div",void 0,["block"])"#));
+  assert!(output.contains("m.p(_tpl"));
+  assert!(output.contains(r#"m.q('<div title="42">false7<b>x</b><b></b></div>')"#));
+  assert!(output.contains(r#"i.hidden="false""#));
+  assert!(output.contains(r#"m.j("label",void 0,[.1,"#));
+}
@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use swc_ecma_ast::Id;
+
+use crate::ComponentProp;
+
+struct Node {
+  id: Id,
+  is_default: Option<bool>,
+  pending_deps: usize,
+}
+
+#[derive(Default)]
+struct DependencyGraph {
+  nodes: Vec<Node>,
+  dependents: HashMap<Id, Vec<usize>>,
+  worklist: Vec<usize>,
+}
+
+impl DependencyGraph {
+  fn add_node(&mut self, id: &Id, is_default: Option<bool>, deps: Option<&HashSet<Id>>) {
+    let idx = self.nodes.len();
+    let pending_deps = deps.map_or(0, |deps| deps.len());
+    for dep in deps.into_iter().flatten() {
+      self.dependents.entry(dep.clone()).or_default().push(idx);
+    }
+    self.nodes.push(Node {
+      id: id.clone(),
+      is_default,
+      pending_deps,
+    });
+    if pending_deps == 0 {
+      self.worklist.push(idx);
+    }
+  }
+}
+
+pub(crate) fn propagate_constants<'a>(
+  components: &mut HashMap<Id, HashMap<Id, ComponentProp>>,
+  states: impl Iterator<Item = (&'a Id, &'a HashSet<Id>)>,
+) -> (HashMap<Id, bool>, HashSet<Id>) {
+  let mut graph = DependencyGraph::default();
+  for prop in components.values().flat_map(|props| props.values()) {
+    if prop.is_escaped {
+      continue;
+    }
+    let is_const = prop.is_folded || prop.deps.is_empty() || !prop.is_used || prop.common_value.is_some();
+    graph.add_node(
+      &prop.local_name,
+      Some(prop.is_default),
+      (!is_const).then_some(&prop.deps),
+    );
+  }
+  for (id, deps) in states {
+    graph.add_node(id, None, Some(deps));
+  }
+  let mut const_props_usages: HashMap<Id, bool> = Default::default();
+  let mut stable_states: HashSet<Id> = Default::default();
+  while let Some(idx) = graph.worklist.pop() {
+    let node = &graph.nodes[idx];
+    if let Some(is_default) = node.is_default {
+      const_props_usages.insert(node.id.clone(), is_default);
+    } else {
+      stable_states.insert(node.id.clone());
+    }
+    if let Some(dependents) = graph.dependents.remove(&node.id) {
+      for dependent in dependents {
+        graph.nodes[dependent].pending_deps -= 1;
+        if graph.nodes[dependent].pending_deps == 0 {
+          graph.worklist.push(dependent);
+        }
+      }
+    }
+  }
+  for prop in components.values_mut().flat_map(|props| props.values_mut()) {
+    prop
+      .deps
+      .retain(|dep| !const_props_usages.contains_key(dep) && !stable_states.contains(dep));
+  }
+  (const_props_usages, stable_states)
+}
+
+#[cfg(test)]
+type Components = HashMap<Id, HashMap<Id, ComponentProp>>;
+
+#[cfg(test)]
+fn generate_bundle(components_count: usize) -> String {
+  let mut code = String::from("(function () {\n  var m = mango;\n");
+  for idx in (0..components_count).rev() {
+    code.push_str(&format!(
+      "  var C{idx} = function (props) {{\n    \"use mango_component\";\n    \"use 1\";\n    var v = props.v;\n    var s = m.a(function () {{ return m.b(v) + 1; }}, /* STATE_DEPS */[v]);\n"
+    ));
+    if idx + 1 < components_count {
+      code.push_str(&format!(
+        "    return m.j(\"div\", void 0, [C{}({{ v: m.a(function () {{ return m.b(s); }}, /* STATE_DEPS */[s]) }})]);\n",
+        idx + 1
+      ));
+    } else {
+      code.push_str(
+        "    return m.j(\"div\", void 0, [m.i(function () { return m.b(s); }, /* DYNAMIC_VIEW_DEPS */[s])]);\n",
+      );
+    }
+    code.push_str("  };\n");
+  }
+  code.push_str("  m.o(document.body, [C0({ v: m.a(1) })]);\n})();\n");
+  code
+}
+
+#[cfg(test)]
+fn analyze_bundle(code: &str) -> (Components, Vec<(Id, HashSet<Id>)>) {
+  use swc_common::comments::SingleThreadedComments;
+  use swc_common::{FileName, Mark, SourceMap, GLOBALS};
+  use swc_ecma_ast::*;
+  use swc_ecma_parser::{parse_file_as_script, Syntax};
+  use swc_ecma_transforms::resolver;
+  use swc_ecma_visit::*;
+
+  use crate::{Round1Visitor, RuntimeAliasCollector, StateUsageCollector};
+
+  let cm: std::sync::Arc<SourceMap> = Default::default();
+  let fm = cm.new_source_file(FileName::Anon.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let script = parse_file_as_script(
+    &fm,
+    Syntax::Es(Default::default()),
+    EsVersion::latest(),
+    Some(&comments),
+    &mut vec![],
+  )
+  .expect("failed to parse a js file as a script");
+  GLOBALS.set(&Default::default(), || {
+    let program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+    let mut components = Default::default();
+    program.visit_children_with(&mut Round1Visitor {
+      comments: None,
+      external_usages: &Default::default(),
+      components: &mut components,
+      bailouts: &mut Default::default(),
+      discover_components: true,
+    });
+    let mut runtime_aliases = RuntimeAliasCollector {
+      comments: &comments,
+      aliases: Default::default(),
+    };
+    program.visit_with(&mut runtime_aliases);
+    let mut state_usages = StateUsageCollector::new(&comments, &runtime_aliases.aliases);
+    program.visit_with(&mut state_usages);
+    let states = state_usages
+      .stable_candidates()
+      .map(|(id, deps)| (id.clone(), deps.clone()))
+      .collect();
+    (components, states)
+  })
+}
+
+// Reference implementation rescanning every prop and state until nothing changes.
+#[cfg(test)]
+fn propagate_constants_naive(
+  components: &mut Components,
+  states: &[(Id, HashSet<Id>)],
+) -> (HashMap<Id, bool>, HashSet<Id>) {
+  let mut const_props_usages: HashMap<Id, bool> = Default::default();
+  let mut stable_states: HashSet<Id> = Default::default();
+  loop {
+    let old_size = const_props_usages.len() + stable_states.len();
+    const_props_usages = components
+      .values()
+      .flat_map(|props| {
+        props
+          .iter()
+          .filter(|(_, prop)| !prop.is_escaped)
+          .filter(|(_, prop)| prop.is_folded || prop.deps.is_empty() || !prop.is_used || prop.common_value.is_some())
+          .map(|(_, prop)| (prop.local_name.clone(), prop.is_default))
+      })
+      .collect();
+    loop {
+      let old_size = stable_states.len();
+      for (id, deps) in states {
+        if deps
+          .iter()
+          .all(|dep| const_props_usages.contains_key(dep) || stable_states.contains(dep))
+        {
+          stable_states.insert(id.clone());
+        }
+      }
+      if stable_states.len() == old_size {
+        break;
+      }
+    }
+    if const_props_usages.len() + stable_states.len() == old_size {
+      break;
+    }
+    for prop in components.values_mut().flat_map(|props| props.values_mut()) {
+      prop
+        .deps
+        .retain(|dep| !const_props_usages.contains_key(dep) && !stable_states.contains(dep));
+    }
+  }
+  (const_props_usages, stable_states)
+}
+
+#[cfg(test)]
+fn compare_propagation(components_count: usize) {
+  use std::time::Instant;
+
+  let (components, states) = analyze_bundle(&generate_bundle(components_count));
+  let mut naive_components = components.clone();
+  let start = Instant::now();
+  let naive_result = propagate_constants_naive(&mut naive_components, &states);
+  let naive_duration = start.elapsed();
+  let mut worklist_components = components;
+  let start = Instant::now();
+  let worklist_result = propagate_constants(&mut worklist_components, states.iter().map(|(id, deps)| (id, deps)));
+  let worklist_duration = start.elapsed();
+  assert_eq!(naive_result, worklist_result);
+  assert_eq!(worklist_result.0.len(), components_count);
+  assert_eq!(worklist_result.1.len(), components_count);
+  for (id, props) in &naive_components {
+    for (name, prop) in props {
+      assert_eq!(prop.deps, worklist_components[id][name].deps);
+    }
+  }
+  println!(
+    "{} components: naive {:?}, worklist {:?} ({:.1}x)",
+    components_count,
+    naive_duration,
+    worklist_duration,
+    naive_duration.as_secs_f64() / worklist_duration.as_secs_f64()
+  );
+}
+
+#[test]
+fn test_constant_propagation() {
+  for components_count in [1, 2, 10, 50, 200] {
+    compare_propagation(components_count);
+  }
+}
+
+#[test]
+#[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+fn bench_constant_propagation() {
+  compare_propagation(3000);
+}
@@ -0,0 +1,286 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use swc_common::{SourceMap, Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_codegen::text_writer::JsWriter;
+use swc_ecma_codegen::{Emitter, Node};
+use swc_ecma_visit::*;
+
+use crate::{Annotation, ComponentProp, DependencyGraph, Manifest};
+
+#[derive(Serialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+  pub rounds: usize,
+  pub components: Vec<ComponentReport>,
+  pub pruned_deps: Vec<PrunedDeps>,
+  pub collapsed: Vec<Collapse>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub graph: Option<DependencyGraph>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub manifest: Option<Manifest>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentReport {
+  pub name: String,
+  pub props: Vec<PropReport>,
+  /// Only measured when `Options::measure_sizes` is set.
+  pub original_size: Option<usize>,
+  pub optimized_size: Option<usize>,
+  #[serde(skip)]
+  pub span: Span,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PropReport {
+  pub name: String,
+  pub is_used: bool,
+  pub is_default: bool,
+  pub is_const: bool,
+  /// Dependencies left after propagation, empty for constant props.
+  pub deps: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedDeps {
+  pub annotation: Annotation,
+  pub component: Option<String>,
+  pub before: usize,
+  pub after: usize,
+  #[serde(skip)]
+  pub span: Span,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CollapseKind {
+  Effect,
+  ImmediateEffect,
+  DynamicView,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Collapse {
+  pub kind: CollapseKind,
+  pub component: Option<String>,
+  #[serde(skip)]
+  pub span: Span,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+impl Report {
+  /// Fills `line` and `column` of every entry from the source map the program was parsed with.
+  pub fn locate(&mut self, cm: &SourceMap) {
+    let locate = |span: Span| {
+      let loc = cm.lookup_char_pos(span.lo);
+      (Some(loc.line), Some(loc.col.0 + 1))
+    };
+    for component in &mut self.components {
+      (component.line, component.column) = locate(component.span);
+    }
+    for pruned in &mut self.pruned_deps {
+      (pruned.line, pruned.column) = locate(pruned.span);
+    }
+    for collapse in &mut self.collapsed {
+      (collapse.line, collapse.column) = locate(collapse.span);
+    }
+    if let Some(graph) = &mut self.graph {
+      graph.locate(cm);
+    }
+  }
+  pub(crate) fn record_pruned(&mut self, annotation: Annotation, span: Span, before: usize, after: usize) {
+    if before == after {
+      return;
+    }
+    if let Some(pruned) = self.pruned_deps.iter_mut().find(|pruned| pruned.span == span) {
+      pruned.after = after;
+      return;
+    }
+    self.pruned_deps.push(PrunedDeps {
+      annotation,
+      component: None,
+      before,
+      after,
+      span,
+      line: None,
+      column: None,
+    });
+  }
+  pub(crate) fn record_collapse(&mut self, kind: CollapseKind, span: Span) {
+    self.collapsed.push(Collapse {
+      kind,
+      component: None,
+      span,
+      line: None,
+      column: None,
+    });
+  }
+  pub(crate) fn add_components(
+    &mut self,
+    components: &HashMap<Id, HashMap<Id, ComponentProp>>,
+    const_props: &HashSet<Id>,
+    locations: &ComponentLocator,
+    original_sizes: &HashMap<Id, usize>,
+  ) {
+    for (id, span, size) in &locations.found {
+      let Some(props) = components.get(id) else {
+        continue;
+      };
+      let mut props: Vec<PropReport> = props
+        .iter()
+        .map(|((name, _), prop)| {
+          let mut deps: Vec<String> = prop.deps.iter().map(|dep| dep.0.to_string()).collect();
+          deps.sort();
+          PropReport {
+            name: name.to_string(),
+            is_used: prop.is_used,
+            is_default: prop.is_default,
+            is_const: const_props.contains(&prop.local_name),
+            deps,
+          }
+        })
+        .collect();
+      props.sort_by(|a, b| a.name.cmp(&b.name));
+      self.components.push(ComponentReport {
+        name: id.0.to_string(),
+        props,
+        original_size: original_sizes.get(id).copied(),
+        optimized_size: *size,
+        span: *span,
+        line: None,
+        column: None,
+      });
+    }
+    let component_at = |span: Span| {
+      locations
+        .found
+        .iter()
+        .filter(|(_, component_span, _)| component_span.contains(span))
+        .min_by_key(|(_, component_span, _)| component_span.hi - component_span.lo)
+        .map(|(id, _, _)| id.0.to_string())
+    };
+    for pruned in &mut self.pruned_deps {
+      pruned.component = component_at(pruned.span);
+    }
+    for collapse in &mut self.collapsed {
+      collapse.component = component_at(collapse.span);
+    }
+  }
+}
+
+/// Finds the function expressions of components, optionally measuring their emitted size.
+pub(crate) struct ComponentLocator<'a> {
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  measure_sizes: bool,
+  pub(crate) found: Vec<(Id, Span, Option<usize>)>,
+}
+
+impl<'a> ComponentLocator<'a> {
+  pub(crate) fn new(components: &'a HashMap<Id, HashMap<Id, ComponentProp>>, measure_sizes: bool) -> Self {
+    Self {
+      components,
+      measure_sizes,
+      found: Default::default(),
+    }
+  }
+}
+
+impl Visit for ComponentLocator<'_> {
+  fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&n.name, &n.init) {
+      if init.is_fn_expr() && self.components.contains_key(&id.to_id()) {
+        let size = self.measure_sizes.then(|| emitted_size(&**init));
+        self.found.push((id.to_id(), init.span(), size));
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_fn_decl(&mut self, n: &FnDecl) {
+    if self.components.contains_key(&n.ident.to_id()) {
+      let size = self.measure_sizes.then(|| emitted_size(n));
+      self.found.push((n.ident.to_id(), n.function.span, size));
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_default_decl(&mut self, n: &DefaultDecl) {
+    if let DefaultDecl::Fn(fn_expr @ FnExpr { ident: Some(ident), .. }) = n {
+      if self.components.contains_key(&ident.to_id()) {
+        let size = self.measure_sizes.then(|| emitted_size(fn_expr));
+        self.found.push((ident.to_id(), fn_expr.function.span, size));
+      }
+    }
+    n.visit_children_with(self);
+  }
+}
+
+fn emitted_size<N: Node>(node: &N) -> usize {
+  let cm: Arc<SourceMap> = Default::default();
+  let mut buf = vec![];
+  let mut emitter = Emitter {
+    cfg: swc_ecma_codegen::Config::default().with_minify(true),
+    comments: None,
+    cm: cm.clone(),
+    wr: Box::new(JsWriter::new(cm, "", &mut buf, None)),
+  };
+  node.emit_with(&mut emitter).map_or(0, |_| buf.len())
+}
+
+#[test]
+fn test_report() {
+  use crate::Options;
+
+  let code = r#"
+(function () {
+  var m = mango;
+  var Badge = function (props) {
+    "use mango_component";
+    "use 2";
+    var text = props.text;
+    var tone = props.tone;
+    var log = m.f(function () { console.log(m.b(text), m.b(tone)); }, /* EFFECT_DEPS */[text, tone]);
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /* DYNAMIC_VIEW_DEPS */[text])]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Badge({ text: m.a("new"), tone: m.a(function () { return m.b(count) > 1 ? "red" : "grey"; }, /* STATE_DEPS */[count]) })]);
+})();
+"#;
+  let (_, report) = crate::run_optimizer_with_report(
+    code,
+    Options {
+      measure_sizes: true,
+      ..Default::default()
+    },
+  );
+  let json = serde_json::to_value(&report).unwrap();
+  let badge = &json["components"][0];
+  assert_eq!(badge["name"], "Badge");
+  assert_eq!(badge["props"][0]["name"], "text");
+  assert_eq!(badge["props"][0]["isConst"], true);
+  assert_eq!(badge["props"][1]["name"], "tone");
+  assert_eq!(badge["props"][1]["isConst"], false);
+  assert_eq!(badge["props"][1]["deps"], serde_json::json!(["count"]));
+  assert!(badge["originalSize"].as_u64().unwrap() > badge["optimizedSize"].as_u64().unwrap());
+  assert_eq!(
+    json["prunedDeps"],
+    serde_json::json!([
+      { "annotation": "effectDeps", "component": "Badge", "before": 2, "after": 1, "line": null, "column": null },
+      { "annotation": "dynamicViewDeps", "component": "Badge", "before": 1, "after": 0, "line": null, "column": null },
+    ])
+  );
+  assert_eq!(
+    json["collapsed"],
+    serde_json::json!([{ "kind": "dynamicView", "component": "Badge", "line": null, "column": null }])
+  );
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use swc_common::comments::Comments;
+use swc_ecma_ast::*;
+use swc_ecma_visit::*;
+
+use crate::{get_annotation, Annotation};
+
+// Keep in sync with `runtimeMethods.js` of the JSX plugin.
+pub(crate) const CREATE_STATE: &str = "a";
+pub(crate) const GET_STATE: &str = "b";
+pub(crate) const CREATE_EFFECT: &str = "f";
+pub(crate) const DESTROY_EFFECT: &str = "g";
+pub(crate) const CREATE_ELEMENT: &str = "j";
+pub(crate) const CREATE_LAZY_COMPONENT: &str = "m";
+pub(crate) const CLONE_TEMPLATE: &str = "p";
+pub(crate) const CREATE_STATIC_ELEMENT: &str = "q";
+
+pub(crate) struct RuntimeAliasCollector<'a, C>
+where
+  C: Comments,
+{
+  pub(crate) comments: &'a C,
+  pub(crate) aliases: HashSet<Id>,
+}
+
+impl<C> RuntimeAliasCollector<'_, C>
+where
+  C: Comments,
+{
+  fn is_annotated(&self, expr: &Expr) -> bool {
+    if let Expr::Array(array_expr) = expr {
+      if !matches!(get_annotation(self.comments, array_expr.span), Annotation::None) {
+        return true;
+      }
+      return array_expr.elems.iter().flatten().any(|elem| {
+        if let Expr::Array(array_expr) = &*elem.expr {
+          !matches!(get_annotation(self.comments, array_expr.span), Annotation::None)
+        } else {
+          false
+        }
+      });
+    }
+    false
+  }
+}
+
+impl<C> Visit for RuntimeAliasCollector<'_, C>
+where
+  C: Comments,
+{
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((alias, _)) = runtime_callee(n) {
+      if n.args.iter().any(|arg| self.is_annotated(&arg.expr)) {
+        self.aliases.insert(alias.to_id());
+      }
+    }
+    n.visit_children_with(self);
+  }
+}
+
+pub(crate) fn runtime_callee(n: &CallExpr) -> Option<(&Ident, &str)> {
+  if let Callee::Expr(callee) = &n.callee {
+    if let Expr::Member(MemberExpr {
+      obj,
+      prop: MemberProp::Ident(method),
+      ..
+    }) = &**callee
+    {
+      if let Expr::Ident(alias) = &**obj {
+        return Some((alias, &method.sym));
+      }
+    }
+  }
+  None
+}
+
+pub(crate) fn is_runtime_call(n: &CallExpr, aliases: &HashSet<Id>, method: &str) -> bool {
+  matches!(runtime_callee(n), Some((alias, name)) if name == method && aliases.contains(&alias.to_id()))
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+use swc_common::{comments::Comments, Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::runtime::{is_runtime_call, CREATE_STATE, GET_STATE};
+use crate::{exports, get_annotation, Annotation};
+
+#[derive(Default, Debug)]
+struct LocalState {
+  span: Span,
+  deps: HashSet<Id>,
+  has_opaque_deps: bool,
+}
+
+pub(crate) enum StateStatus<'a> {
+  Escaped(Span),
+  OpaqueDeps(Span),
+  Derived(Span, &'a HashSet<Id>),
+}
+
+pub(crate) struct StateUsageCollector<'a, C>
+where
+  C: Comments,
+{
+  comments: &'a C,
+  aliases: &'a HashSet<Id>,
+  states: HashMap<Id, LocalState>,
+  escaped: HashMap<Id, Span>,
+}
+
+impl<'a, C> StateUsageCollector<'a, C>
+where
+  C: Comments,
+{
+  pub(crate) fn new(comments: &'a C, aliases: &'a HashSet<Id>) -> Self {
+    Self {
+      comments,
+      aliases,
+      states: Default::default(),
+      escaped: Default::default(),
+    }
+  }
+  pub(crate) fn stable_candidates(&self) -> impl Iterator<Item = (&Id, &HashSet<Id>)> {
+    self
+      .states
+      .iter()
+      .filter(|(id, state)| !self.escaped.contains_key(*id) && !state.has_opaque_deps)
+      .map(|(id, state)| (id, &state.deps))
+  }
+  pub(crate) fn status(&self, id: &Id) -> Option<StateStatus<'_>> {
+    let state = self.states.get(id)?;
+    Some(if let Some(span) = self.escaped.get(id) {
+      StateStatus::Escaped(*span)
+    } else if state.has_opaque_deps {
+      StateStatus::OpaqueDeps(state.span)
+    } else {
+      StateStatus::Derived(state.span, &state.deps)
+    })
+  }
+  pub(crate) fn states(&self) -> impl Iterator<Item = (&Id, Span, &HashSet<Id>)> {
+    self.states.iter().map(|(id, state)| (id, state.span, &state.deps))
+  }
+  fn escape(&mut self, id: Id, span: Span) {
+    self.escaped.entry(id).or_insert(span);
+  }
+  fn is_annotated(&self, n: &ArrayLit) -> bool {
+    !matches!(get_annotation(self.comments, n.span), Annotation::None)
+  }
+}
+
+impl<C> Visit for StateUsageCollector<'_, C>
+where
+  C: Comments,
+{
+  fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&n.name, &n.init) {
+      if let Expr::Call(call_expr) = &**init {
+        if is_runtime_call(call_expr, self.aliases, CREATE_STATE) {
+          let mut state = LocalState {
+            span: n.span,
+            ..Default::default()
+          };
+          if let Some(ExprOrSpread { expr, .. }) = call_expr.args.get(1) {
+            if let Expr::Array(array_expr) = &**expr {
+              for elem in array_expr.elems.iter().flatten() {
+                match &*elem.expr {
+                  Expr::Ident(dep) => {
+                    state.deps.insert(dep.to_id());
+                  }
+                  Expr::Cond(CondExpr { cons, .. }) => match &**cons {
+                    Expr::Ident(dep) => {
+                      state.deps.insert(dep.to_id());
+                    }
+                    _ => state.has_opaque_deps = true,
+                  },
+                  _ => state.has_opaque_deps = true,
+                }
+              }
+            } else {
+              state.has_opaque_deps = true;
+            }
+          }
+          self.states.insert(id.to_id(), state);
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if is_runtime_call(n, self.aliases, GET_STATE) {
+      if let Some(ExprOrSpread { expr, spread: None }) = n.args.first() {
+        if expr.is_ident() {
+          n.args[1..].visit_with(self);
+          return;
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_array_lit(&mut self, n: &ArrayLit) {
+    if !self.is_annotated(n) {
+      n.visit_children_with(self);
+      return;
+    }
+    for elem in n.elems.iter().flatten() {
+      match &*elem.expr {
+        Expr::Ident(_) => {}
+        Expr::Cond(CondExpr { test, cons, alt, .. }) if cons.is_ident() => {
+          test.visit_with(self);
+          alt.visit_with(self);
+        }
+        expr => expr.visit_with(self),
+      }
+    }
+  }
+  fn visit_expr(&mut self, n: &Expr) {
+    if let Expr::Ident(ident) = n {
+      self.escape(ident.to_id(), ident.span);
+    } else {
+      n.visit_children_with(self);
+    }
+  }
+  fn visit_simple_assign_target(&mut self, n: &SimpleAssignTarget) {
+    if let SimpleAssignTarget::Ident(BindingIdent { id, .. }) = n {
+      self.escape(id.to_id(), id.span);
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_assign_target_pat(&mut self, n: &AssignTargetPat) {
+    for id in find_pat_ids::<_, Id>(n) {
+      self.escape(id, n.span());
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_module_decl(&mut self, n: &ModuleDecl) {
+    for (state, _) in exports(n) {
+      self.escape(state.to_id(), state.span);
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_prop(&mut self, n: &Prop) {
+    if let Prop::Shorthand(ident) = n {
+      self.escape(ident.to_id(), ident.span);
+    }
+    n.visit_children_with(self);
+  }
+}
+
+#[test]
+fn test_stable_dynamic_views() {
+  let output = crate::run_optimizer(
+    r#"
+(function () {
+  var m = mango;
+  var Counter = function () {
+    "use mango_component";
+    "use 0";
+    var label = m.a("Count");
+    var count = m.a(0);
+    var title = m.a(function () { return m.b(label) + ":"; }, /* STATE_DEPS */[label]);
+    return m.j("div", void 0, [
+      m.i(function () { return m.b(title); }, /* DYNAMIC_VIEW_DEPS */[title]),
+      m.j("span", void 0, function () { return [function () { return m.b(label); }, /* DYNAMIC_VIEW_DEPS */[label]]; }),
+      m.i(function () { return m.b(count); }, /* DYNAMIC_VIEW_DEPS */[count]),
+      m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]])
+    ]);
+  };
+  m.o(document.body, [Counter()]);
+})();
+"#,
+  );
+  assert!(output.contains(r#"m.j("div",void 0,[m.b(title),m.j("span",void 0,[m.b(label)])"#));
+  assert!(output.contains("m.i(function(){return m.b(count);},[count])"));
+}
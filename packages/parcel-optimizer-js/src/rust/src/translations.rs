@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use swc_common::errors::HANDLER;
+use swc_common::{Span, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_utils::{collect_decls, quote_ident};
+use swc_ecma_visit::*;
+
+const TRANSLATION: &str = "MANGO_TRANSLATION";
+
+/// Translations of a locale, keyed by id. Nested objects are flattened into dotted ids, e.g. `nav.home`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Dictionary {
+  /// Path to a JSON file.
+  Path(String),
+  Inline(Map<String, Value>),
+}
+
+impl Dictionary {
+  fn load(&self) -> Result<BTreeMap<String, String>, String> {
+    let inline;
+    let entries = match self {
+      Dictionary::Path(path) => {
+        let json = std::fs::read_to_string(path).map_err(|err| format!("failed to read `{}`: {}", path, err))?;
+        inline = serde_json::from_str::<Map<String, Value>>(&json)
+          .map_err(|err| format!("invalid dictionary `{}`: {}", path, err))?;
+        &inline
+      }
+      Dictionary::Inline(entries) => entries,
+    };
+    let mut translations = BTreeMap::new();
+    flatten(entries, "", &mut translations);
+    Ok(translations)
+  }
+}
+
+fn flatten(entries: &Map<String, Value>, prefix: &str, translations: &mut BTreeMap<String, String>) {
+  for (key, value) in entries {
+    let id = if prefix.is_empty() {
+      key.clone()
+    } else {
+      format!("{}.{}", prefix, key)
+    };
+    match value {
+      Value::Object(entries) => flatten(entries, &id, translations),
+      Value::String(message) => {
+        translations.insert(id, message.clone());
+      }
+      value => {
+        translations.insert(id, value.to_string());
+      }
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+  Text(String),
+  Param(String),
+  Child(usize),
+}
+
+/// Parses the subset of ICU messages made of text, `{param}` arguments and `{0}` children, with `'` quoting.
+/// Returns `None` for anything else, e.g. plurals, which are left to the runtime.
+fn parse_message(message: &str) -> Option<Vec<Segment>> {
+  let mut segments = vec![];
+  let mut text = String::new();
+  let mut chars = message.chars().peekable();
+  while let Some(char) = chars.next() {
+    match char {
+      '\'' => match chars.peek() {
+        Some('\'') => {
+          chars.next();
+          text.push('\'');
+        }
+        Some('{' | '}') => loop {
+          match chars.next()? {
+            '\'' if chars.peek() == Some(&'\'') => {
+              chars.next();
+              text.push('\'');
+            }
+            '\'' => break,
+            char => text.push(char),
+          }
+        },
+        _ => text.push('\''),
+      },
+      '{' => {
+        let mut argument = String::new();
+        loop {
+          match chars.next()? {
+            '}' => break,
+            '{' | ',' => return None,
+            char => argument.push(char),
+          }
+        }
+        let argument = argument.trim();
+        let segment = match argument.parse() {
+          Ok(index) => Segment::Child(index),
+          Err(_) if !argument.is_empty() => Segment::Param(argument.to_string()),
+          Err(_) => return None,
+        };
+        if !text.is_empty() {
+          segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        segments.push(segment);
+      }
+      '}' => return None,
+      char => text.push(char),
+    }
+  }
+  if !text.is_empty() {
+    segments.push(Segment::Text(text));
+  }
+  Some(segments)
+}
+
+/// Matches calls of the `MANGO_TRANSLATION` global, as opposed to a binding of the program shadowing it.
+fn is_translation_call<'a>(n: &'a CallExpr, decls: &HashSet<Id>) -> Option<(&'a str, Span)> {
+  let Callee::Expr(callee) = &n.callee else {
+    return None;
+  };
+  match (&**callee, n.args.first().map(|arg| &*arg.expr)) {
+    (Expr::Ident(callee), Some(Expr::Lit(Lit::Str(id))))
+      if callee.sym == TRANSLATION && !decls.contains(&callee.to_id()) =>
+    {
+      Some((id.value.as_str(), id.span))
+    }
+    _ => None,
+  }
+}
+
+fn concat(parts: Vec<Expr>) -> Expr {
+  let mut parts = parts.into_iter();
+  let first = match parts.next() {
+    Some(first @ Expr::Lit(Lit::Str(_))) => first,
+    // Keeps `+` a concatenation when the message starts with a param, e.g. a number.
+    Some(first) => Expr::Bin(BinExpr {
+      span: DUMMY_SP,
+      op: op!(bin, "+"),
+      left: Box::new(str_lit(String::new())),
+      right: Box::new(first),
+    }),
+    None => return str_lit(String::new()),
+  };
+  parts.fold(first, |left, right| {
+    Expr::Bin(BinExpr {
+      span: DUMMY_SP,
+      op: op!(bin, "+"),
+      left: Box::new(left),
+      right: Box::new(right),
+    })
+  })
+}
+
+fn str_lit(value: String) -> Expr {
+  Expr::Lit(Lit::Str(value.into()))
+}
+
+/// Builds the translated expression, or `None` when params or children aren't literal enough to substitute.
+/// Params used other than once are only substituted when they are identifiers or literals, to keep side effects.
+fn translate(segments: Vec<Segment>, n: &CallExpr) -> Option<Expr> {
+  let mut params: HashMap<String, &Expr> = Default::default();
+  match n.args.get(1).map(|arg| (&*arg.expr, arg.spread)) {
+    None => {}
+    Some((Expr::Ident(ident), None)) if ident.sym == "undefined" => {}
+    Some((Expr::Object(object), None)) => {
+      for prop in &object.props {
+        let (key, value) = match prop.as_prop().map(|prop| &**prop)? {
+          Prop::KeyValue(KeyValueProp { key, value }) => match key {
+            PropName::Ident(key) => (key.sym.to_string(), &**value),
+            PropName::Str(key) => (key.value.to_string(), &**value),
+            _ => return None,
+          },
+          _ => return None,
+        };
+        params.insert(key, value);
+      }
+    }
+    _ => return None,
+  }
+  let mut children = vec![];
+  for arg in n.args.iter().skip(2) {
+    if arg.spread.is_some() {
+      return None;
+    }
+    if !matches!(&*arg.expr, Expr::Lit(Lit::Str(Str { value, .. })) if value == " ") {
+      children.push(&*arg.expr);
+    }
+  }
+  let mut references: HashMap<&str, usize> = params.keys().map(|key| (key.as_str(), 0)).collect();
+  for segment in &segments {
+    if let Segment::Param(name) = segment {
+      *references.get_mut(name.as_str())? += 1;
+    }
+  }
+  if references
+    .iter()
+    .any(|(name, count)| *count != 1 && !matches!(params[*name], Expr::Ident(_) | Expr::Lit(_)))
+  {
+    return None;
+  }
+  let has_children = segments.iter().any(|segment| matches!(segment, Segment::Child(_)));
+  let mut elems = vec![];
+  let mut parts = vec![];
+  for segment in segments {
+    match segment {
+      Segment::Text(text) => parts.push(str_lit(text)),
+      Segment::Param(name) => parts.push(params[&name].clone()),
+      Segment::Child(index) => {
+        if !parts.is_empty() {
+          elems.push(concat(std::mem::take(&mut parts)));
+        }
+        elems.push(match children.get(index) {
+          Some(child) => (*child).clone(),
+          None => Expr::Ident(quote_ident!("undefined").into()),
+        });
+      }
+    }
+  }
+  if !has_children {
+    return Some(concat(parts));
+  }
+  if !parts.is_empty() {
+    elems.push(concat(parts));
+  }
+  Some(Expr::Array(ArrayLit {
+    span: n.span,
+    elems: elems.into_iter().map(|expr| Some(expr.into())).collect(),
+  }))
+}
+
+/// A `MANGO_TRANSLATION` call listed in a translation catalog.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationCall {
+  pub id: String,
+  /// Original file of the call when the input source map covers it, or the bundle otherwise.
+  pub source: Option<String>,
+  pub line: usize,
+  pub column: usize,
+  pub params: Vec<String>,
+  /// Whether the call made it to the output rather than being removed as dead code.
+  pub is_live: bool,
+}
+
+/// Collects calls with a literal id, along with the span of the id and the names of the params.
+pub(crate) struct TranslationCollector {
+  decls: HashSet<Id>,
+  pub(crate) calls: Vec<(Span, String, Vec<String>)>,
+}
+
+impl TranslationCollector {
+  pub(crate) fn new(program: &Program) -> Self {
+    Self {
+      decls: collect_decls::<Id, _>(program).into_iter().collect(),
+      calls: vec![],
+    }
+  }
+}
+
+impl Visit for TranslationCollector {
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if let Some((id, span)) = is_translation_call(n, &self.decls) {
+      let params = match n.args.get(1).map(|arg| &*arg.expr) {
+        Some(Expr::Object(object)) => object
+          .props
+          .iter()
+          .filter_map(|prop| match &**prop.as_prop()? {
+            Prop::KeyValue(KeyValueProp {
+              key: PropName::Ident(key),
+              ..
+            }) => Some(key.sym.to_string()),
+            Prop::KeyValue(KeyValueProp {
+              key: PropName::Str(key),
+              ..
+            }) => Some(key.value.to_string()),
+            Prop::Shorthand(key) => Some(key.sym.to_string()),
+            _ => None,
+          })
+          .collect(),
+        _ => vec![],
+      };
+      self.calls.push((span, id.to_string(), params));
+    }
+    n.visit_children_with(self);
+  }
+}
+
+/// Replaces `MANGO_TRANSLATION(id, params, ...children)` calls with their translation in a locale.
+struct TranslationInliner<'a> {
+  translations: &'a BTreeMap<String, String>,
+  decls: HashSet<Id>,
+  used: HashSet<String>,
+}
+
+impl VisitMut for TranslationInliner<'_> {
+  fn visit_mut_expr(&mut self, n: &mut Expr) {
+    n.visit_mut_children_with(self);
+    let Expr::Call(call) = n else {
+      return;
+    };
+    let Some((id, span)) = is_translation_call(call, &self.decls) else {
+      return;
+    };
+    self.used.insert(id.to_string());
+    match self.translations.get(id) {
+      Some(message) => {
+        if let Some(translated) = parse_message(message).and_then(|segments| translate(segments, call)) {
+          *n = translated;
+        }
+      }
+      None if HANDLER.is_set() => {
+        let message = format!("translation `{}` is missing from the dictionary", id);
+        HANDLER.with(|handler| handler.struct_span_warn(span, &message).emit());
+      }
+      None => {}
+    }
+  }
+}
+
+/// Inlines translations of `dictionary`, warning about ids missing from it. Ids it defines but the program never
+/// uses are only reported with `report_unused`, as a bundle only uses the ids of its own chunk of the app.
+pub(crate) fn inline_translations(program: &mut Program, dictionary: &Dictionary, report_unused: bool) {
+  let translations = match dictionary.load() {
+    Ok(translations) => translations,
+    Err(message) => {
+      if HANDLER.is_set() {
+        HANDLER.with(|handler| handler.struct_err(&message).emit());
+      }
+      return;
+    }
+  };
+  let mut inliner = TranslationInliner {
+    translations: &translations,
+    decls: collect_decls::<Id, _>(program).into_iter().collect(),
+    used: Default::default(),
+  };
+  program.visit_mut_with(&mut inliner);
+  if report_unused && HANDLER.is_set() {
+    for id in translations.keys().filter(|id| !inliner.used.contains(*id)) {
+      let message = format!("translation `{}` is defined but never used", id);
+      HANDLER.with(|handler| handler.struct_warn(&message).emit());
+    }
+  }
+}
+
+#[test]
+fn test_translations() {
+  assert_eq!(
+    parse_message("It's '{'{name}'}' ''{0}''"),
+    Some(vec![
+      Segment::Text("It's {".into()),
+      Segment::Param("name".into()),
+      Segment::Text("} '".into()),
+      Segment::Child(0),
+      Segment::Text("'".into()),
+    ])
+  );
+  assert_eq!(parse_message("{count, plural, one {# item} other {# items}}"), None);
+
+  let code = r#"
+(function () {
+  var m = mango;
+  var name = m.a("Ada");
+  m.o(document.body, [
+    MANGO_TRANSLATION("nav.home", undefined),
+    MANGO_TRANSLATION("greeting", { name: m.b(name) }),
+    MANGO_TRANSLATION("count", { n: 1 }),
+    MANGO_TRANSLATION("terms", undefined, " ", m.j("a")),
+    MANGO_TRANSLATION("echo", { name: m.b(name) }),
+    MANGO_TRANSLATION("missing", undefined),
+    (function (MANGO_TRANSLATION) { return MANGO_TRANSLATION("nav.home", undefined); })(m.t),
+  ]);
+})();
+"#;
+  let options = crate::Options {
+    dictionary: Some(Dictionary::Inline(
+      serde_json::from_value(serde_json::json!({
+        "nav": { "home": "Home" },
+        "greeting": "Hello, {name}!",
+        "count": "{n} items",
+        "terms": "Accept the {0}.",
+        "echo": "{name} {name}",
+        "unused": "Never shown",
+      }))
+      .unwrap(),
+    )),
+    ..Default::default()
+  };
+  let (output, _) = crate::run_optimizer_with_report(code, options.clone());
+  assert!(output.contains(r#""Home","Hello, "+m.b(name)+"!",""+1+" items",["Accept the ",m.j("a"),"."],"#));
+  assert!(output.contains(r#"MANGO_TRANSLATION("echo",{name:m.b(name)})"#));
+  assert!(output.contains(r#"return MANGO_TRANSLATION("nav.home",undefined);"#));
+  assert_eq!(
+    crate::run_optimizer_with_diagnostics(code, options.clone()),
+    vec!["warning: translation `missing` is missing from the dictionary"]
+  );
+  let options = crate::Options {
+    report_unused_translations: true,
+    ..options
+  };
+  assert_eq!(
+    crate::run_optimizer_with_diagnostics(code, options),
+    vec![
+      "warning: translation `missing` is missing from the dictionary",
+      "warning: translation `unused` is defined but never used",
+    ]
+  );
+}
@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+use swc_common::comments::Comments;
+use swc_common::errors::HANDLER;
+use swc_common::{Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_utils::*;
+use swc_ecma_visit::*;
+
+use crate::runtime::{is_runtime_call, CREATE_STATE};
+use crate::{get_annotation, preamble_props_count, Annotation, ComponentProp};
+
+pub(crate) fn collect_bindings(program: &Program) -> HashSet<Id> {
+  collect_decls::<Id, _>(program).into_iter().collect()
+}
+
+/// Re-walks the optimized program and reports, as errors, structural invariants that no longer hold.
+pub(crate) struct InvariantChecker<'a, C>
+where
+  C: Comments,
+{
+  comments: &'a C,
+  aliases: &'a HashSet<Id>,
+  components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+  bindings_before: &'a HashSet<Id>,
+  bindings: HashSet<Id>,
+}
+
+impl<'a, C> InvariantChecker<'a, C>
+where
+  C: Comments,
+{
+  pub(crate) fn new(
+    comments: &'a C,
+    aliases: &'a HashSet<Id>,
+    components: &'a HashMap<Id, HashMap<Id, ComponentProp>>,
+    bindings_before: &'a HashSet<Id>,
+  ) -> Self {
+    Self {
+      comments,
+      aliases,
+      components,
+      bindings_before,
+      bindings: Default::default(),
+    }
+  }
+  pub(crate) fn run(&mut self, program: &Program) {
+    self.bindings = collect_bindings(program);
+    program.visit_with(self);
+  }
+  fn check_preamble(&self, component: &Ident, function: &Function) {
+    if let (true, Some(body)) = (self.components.contains_key(&component.to_id()), &function.body) {
+      let expected = preamble_props_count(body).unwrap_or_default().max(0) as usize;
+      let declared: usize = body
+        .stmts
+        .iter()
+        .skip(2)
+        .map_while(|stmt| match stmt {
+          Stmt::Decl(Decl::Var(var_decl)) => Some(var_decl.decls.len()),
+          _ => None,
+        })
+        .sum();
+      if declared < expected {
+        violation(
+          function.span,
+          format!(
+            "preamble of `{}` declares {} props, but only {} declarations follow it",
+            component.sym, expected, declared
+          ),
+        );
+      }
+    }
+  }
+  fn check_deps(&self, n: &ArrayLit, annotation: Annotation) {
+    for (idx, elem) in n.elems.iter().enumerate() {
+      let Some(ExprOrSpread { expr, spread: None }) = elem else {
+        violation(
+          n.span,
+          format!("{} array has a hole or a spread", annotation_name(annotation)),
+        );
+        continue;
+      };
+      let dep = match &**expr {
+        Expr::Ident(ident) => ident,
+        Expr::Cond(CondExpr { cons, .. }) if cons.is_ident() => cons.as_ident().unwrap(),
+        Expr::Fn(_) if idx == 0 && annotation == Annotation::DynamicAttrs => continue,
+        _ => {
+          violation(
+            expr.span(),
+            format!("{} array may only contain identifiers", annotation_name(annotation)),
+          );
+          continue;
+        }
+      };
+      if !self.bindings.contains(&dep.to_id()) {
+        violation(
+          dep.span,
+          format!(
+            "`{}` in {} array is not bound in scope",
+            dep.sym,
+            annotation_name(annotation)
+          ),
+        );
+      }
+    }
+  }
+}
+
+impl<C> Visit for InvariantChecker<'_, C>
+where
+  C: Comments,
+{
+  fn visit_array_lit(&mut self, n: &ArrayLit) {
+    let annotation = get_annotation(self.comments, n.span);
+    if annotation != Annotation::None {
+      self.check_deps(n, annotation);
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_call_expr(&mut self, n: &CallExpr) {
+    if is_runtime_call(n, self.aliases, CREATE_STATE) {
+      if let [value, ExprOrSpread { expr: deps, .. }, ..] = &n.args[..] {
+        if deps.is_array() && !value.expr.is_fn_expr() && !value.expr.is_arrow() {
+          violation(n.span, "computed state has no function to compute its value".into());
+        }
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_ident(&mut self, n: &Ident) {
+    let id = n.to_id();
+    if self.bindings_before.contains(&id) && !self.bindings.contains(&id) {
+      violation(
+        n.span,
+        format!("`{}` references a binding that was removed by the optimizer", n.sym),
+      );
+    }
+  }
+  fn visit_labeled_stmt(&mut self, n: &LabeledStmt) {
+    n.body.visit_with(self);
+  }
+  fn visit_break_stmt(&mut self, _: &BreakStmt) {}
+  fn visit_continue_stmt(&mut self, _: &ContinueStmt) {}
+  fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&n.name, &n.init) {
+      if let Expr::Fn(FnExpr { function, .. }) = &**init {
+        self.check_preamble(id, function);
+      }
+    }
+    n.visit_children_with(self);
+  }
+  fn visit_fn_decl(&mut self, n: &FnDecl) {
+    self.check_preamble(&n.ident, &n.function);
+    n.visit_children_with(self);
+  }
+  fn visit_default_decl(&mut self, n: &DefaultDecl) {
+    if let DefaultDecl::Fn(FnExpr {
+      ident: Some(ident),
+      function,
+    }) = n
+    {
+      self.check_preamble(ident, function);
+    }
+    n.visit_children_with(self);
+  }
+}
+
+fn annotation_name(annotation: Annotation) -> &'static str {
+  match annotation {
+    Annotation::None => "unannotated",
+    Annotation::EffectDeps => "EFFECT_DEPS",
+    Annotation::ImmediateEffectDeps => "IMMEDIATE_EFFECT_DEPS",
+    Annotation::StateDeps => "STATE_DEPS",
+    Annotation::DynamicAttrs => "DYNAMIC_ATTRS",
+    Annotation::DynamicViewDeps => "DYNAMIC_VIEW_DEPS",
+  }
+}
+
+fn violation(span: Span, message: String) {
+  HANDLER.with(|handler| handler.struct_span_err(span, &message).emit());
+}
+
+#[test]
+fn test_invariants() {
+  let diagnostics = crate::run_optimizer_with_diagnostics(
+    r#"
+(function () {
+  var m = mango;
+  var Card = function (props) {
+    "use mango_component";
+    "use 2";
+    var title = props.title;
+    m.f(function () { console.log(m.b(title)); }, /* EFFECT_DEPS */[title, ghost]);
+    var upper = m.a("TITLE", /* STATE_DEPS */[title]);
+    return m.j("div", void 0, [m.i(function () { return m.b(upper); }, /* DYNAMIC_VIEW_DEPS */[upper, m.b(upper)])]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Card({ title: count, body: count })]);
+})();
+"#,
+    crate::Options {
+      verify: true,
+      ..Default::default()
+    },
+  );
+  assert_eq!(
+    diagnostics,
+    vec![
+      "error: preamble of `Card` declares 2 props, but only 1 declarations follow it",
+      "error: `ghost` in EFFECT_DEPS array is not bound in scope",
+      "error: computed state has no function to compute its value",
+      "error: DYNAMIC_VIEW_DEPS array may only contain identifiers",
+    ]
+  );
+
+  use swc_common::{comments::SingleThreadedComments, FileName, Mark};
+  use swc_ecma_parser::{parse_file_as_script, Syntax};
+  use swc_ecma_transforms::resolver;
+
+  let stderr = testing::run_test(false, |cm, _| {
+    let fm = cm.new_source_file(FileName::Anon.into(), "var gone = 1; console.log(gone);".into());
+    let script = parse_file_as_script(
+      &fm,
+      Syntax::Es(Default::default()),
+      EsVersion::latest(),
+      None,
+      &mut vec![],
+    )
+    .unwrap();
+    let mut program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+    let bindings_before = collect_bindings(&program);
+    program.as_mut_script().unwrap().body.remove(0);
+    InvariantChecker::new(
+      &SingleThreadedComments::default(),
+      &Default::default(),
+      &Default::default(),
+      &bindings_before,
+    )
+    .run(&program);
+    Err::<(), _>(())
+  })
+  .unwrap_err();
+  assert!(stderr
+    .to_string()
+    .contains("`gone` references a binding that was removed by the optimizer"));
+}
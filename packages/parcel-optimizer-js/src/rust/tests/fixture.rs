@@ -0,0 +1,52 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mango_optimizer_js::{optimize, Options};
+use swc_common::Mark;
+use swc_core::ecma::transforms::testing::{test_fixture, FixtureTestConfig};
+use swc_ecma_ast::fn_pass;
+use swc_ecma_parser::Syntax;
+use swc_ecma_transforms::resolver;
+
+/// Runs the optimizer over `tests/fixture/<case>/input.js` and compares the result with `output.js`.
+/// Options are read from an optional `options.json`, and every output is checked for invariants.
+/// Run with `UPDATE=1` to update the snapshots.
+#[testing::fixture("tests/fixture/**/input.js")]
+fn fixture(input: PathBuf) {
+  run_fixture(&input, &input.with_file_name("output.js"), false);
+}
+
+/// Same as `fixture`, but parses `input.mjs` as an ES module.
+#[testing::fixture("tests/fixture/**/input.mjs")]
+fn module_fixture(input: PathBuf) {
+  run_fixture(&input, &input.with_file_name("output.mjs"), true);
+}
+
+fn run_fixture(input: &Path, output: &Path, module: bool) {
+  let dir = input.parent().unwrap();
+  let options = Options {
+    verify: true,
+    ..fs::read_to_string(dir.join("options.json"))
+      .map(|json| serde_json::from_str(&json).unwrap())
+      .unwrap_or_default()
+  };
+  test_fixture(
+    Syntax::Es(Default::default()),
+    &|tester| {
+      let comments = tester.comments.clone();
+      let options = options.clone();
+      (
+        resolver(Mark::new(), Mark::new(), false),
+        fn_pass(move |program| {
+          optimize(program, &comments, &options);
+        }),
+      )
+    },
+    input,
+    output,
+    FixtureTestConfig {
+      module: Some(module),
+      ..Default::default()
+    },
+  );
+}
@@ -0,0 +1,11 @@
+(function () {
+  var m = mango;
+  var Item = function (props) {
+    "use mango_component";
+    "use 1";
+    var label = props.label;
+    return m.j("li", void 0, [m.i(function () { return m.b(label); }, /* DYNAMIC_VIEW_DEPS */[label])]);
+  };
+  var components = [Item];
+  m.o(document.body, [Item({ label: m.a("one") })]);
+})();
@@ -0,0 +1,10 @@
+(function () {
+  var m = mango;
+  var Chart = function (props) {
+    "use mango_component";
+    "use 1";
+    var points = props.points;
+    return m.j("svg", void 0, [m.i(function () { return m.b(points).length; }, /* DYNAMIC_VIEW_DEPS */[points])]);
+  };
+  m.o(document.body, [Chart({ points: m.a([]) }), m.m("chart.js", Chart)]);
+})();
@@ -0,0 +1,21 @@
+(function() {
+    var m = mango;
+    var Chart = function(props) {
+        "use mango_component";
+        "use 1";
+        var points = props.points;
+        return m.j("svg", void 0, [
+            m.i(function() {
+                return m.b(points).length;
+            }, [
+                points
+            ])
+        ]);
+    };
+    m.o(document.body, [
+        Chart({
+            points: m.a([])
+        }),
+        m.m("chart.js", Chart)
+    ]);
+})();
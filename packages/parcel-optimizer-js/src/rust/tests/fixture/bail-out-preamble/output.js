@@ -0,0 +1,19 @@
+(function() {
+    var m = mango;
+    var Broken = function(props) {
+        "use mango_component";
+        var label = props.label;
+        return m.j("b", void 0, [
+            m.i(function() {
+                return m.b(label);
+            }, [
+                label
+            ])
+        ]);
+    };
+    m.o(document.body, [
+        Broken({
+            label: m.a("x")
+        })
+    ]);
+})();
@@ -0,0 +1,10 @@
+(function () {
+  var m = mango;
+  var Card = function (props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    return m.j("div", void 0, [m.i(function () { return m.b(title); }, /* DYNAMIC_VIEW_DEPS */[title])]);
+  };
+  m.o(document.body, [Card({ title: m.a("Hi") })]);
+})();
@@ -0,0 +1,25 @@
+(function() {
+    var m = mango;
+    var Card = function(props) {
+        "use mango_component";
+        "use 1";
+        var title = props.title;
+        title instanceof Array && m.f(function() {
+            console.error(new Error("mango: prop `title` of `Card` was assumed constant, but its state was set"));
+        }, [
+            title
+        ]);
+        return m.j("div", void 0, [
+            m.i(function() {
+                return m.b(title);
+            }, [
+                title
+            ])
+        ]);
+    };
+    m.o(document.body, [
+        Card({
+            title: m.a("Hi")
+        })
+    ]);
+})();
@@ -0,0 +1,37 @@
+(function() {
+    var m = mango;
+    var Button = function(props) {
+        "use mango_component";
+        "use 4";
+        var size = "md";
+        var _u = props.size !== undefined;
+        var tone = props.tone || "grey";
+        var _u2 = props.tone !== undefined;
+        return m.j("button", [
+            [
+                function(i) {
+                    i.className = size + " " + (_u2 ? m.b(tone) : tone);
+                },
+                _u2 ? tone : undefined
+            ]
+        ]);
+    };
+    var tone = m.a("red");
+    m.j("button", [
+        [
+            function(i) {
+                i.onclick = function() {
+                    m.c(tone, "blue");
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Button({
+            tone: tone
+        }),
+        Button({
+            tone: m.a("green")
+        })
+    ]);
+})();
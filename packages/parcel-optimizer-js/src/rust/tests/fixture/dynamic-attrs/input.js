@@ -0,0 +1,13 @@
+(function () {
+  var m = mango;
+  var Link = function (props) {
+    "use mango_component";
+    "use 2";
+    var href = props.href;
+    var active = props.active;
+    return m.j("a", [/* DYNAMIC_ATTRS */[function (i) { i.href = m.b(href); }, href], /* DYNAMIC_ATTRS */[function (i) { i.className = m.b(active) ? "active" : ""; }, active]]);
+  };
+  var active = m.a(false);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(active, !m.b(active)); }; }]]);
+  m.o(document.body, [Link({ href: m.a("/home"), active: active })]);
+})();
@@ -0,0 +1,13 @@
+(function () {
+  var m = mango;
+  var Greeting = function (props) {
+    "use mango_component";
+    "use 2";
+    var name = props.name;
+    var mood = props.mood;
+    return m.j("p", void 0, [m.i(function () { return m.b(name); }, /* DYNAMIC_VIEW_DEPS */[name]), m.i(function () { return m.b(mood); }, /* DYNAMIC_VIEW_DEPS */[mood]), function () { return [function () { return m.b(name) + "!"; }, /* DYNAMIC_VIEW_DEPS */[name]]; }]);
+  };
+  var mood = m.a("happy");
+  m.j("input", [/* DYNAMIC_ATTRS */[function (i) { i.oninput = function () { m.c(mood, i.value); }; }]]);
+  m.o(document.body, [Greeting({ name: m.a("Ada"), mood: mood })]);
+})();
@@ -0,0 +1,36 @@
+(function() {
+    var m = mango;
+    var Greeting = function(props) {
+        "use mango_component";
+        "use 2";
+        var name = props.name;
+        var mood = props.mood;
+        return m.j("p", void 0, [
+            name,
+            m.i(function() {
+                return m.b(mood);
+            }, [
+                mood
+            ]),
+            [
+                name + "!"
+            ]
+        ]);
+    };
+    var mood = m.a("happy");
+    m.j("input", [
+        [
+            function(i) {
+                i.oninput = function() {
+                    m.c(mood, i.value);
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Greeting({
+            name: "Ada",
+            mood: mood
+        })
+    ]);
+})();
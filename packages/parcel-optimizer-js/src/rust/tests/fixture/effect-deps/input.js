@@ -0,0 +1,15 @@
+(function () {
+  var m = mango;
+  var Logger = function (props) {
+    "use mango_component";
+    "use 2";
+    var label = props.label;
+    var value = props.value;
+    var both = m.f(function () { console.log(m.b(label), m.b(value)); }, /* EFFECT_DEPS */[label, value]);
+    var once = m.f(function () { console.log(m.b(label)); }, /* EFFECT_DEPS */[label]);
+    return m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.g(both), m.g(once); }; }]]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* DYNAMIC_ATTRS */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Logger({ label: m.a("count"), value: count })]);
+})();
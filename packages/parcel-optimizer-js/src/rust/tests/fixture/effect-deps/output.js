@@ -0,0 +1,39 @@
+(function() {
+    var m = mango;
+    var Logger = function(props) {
+        "use mango_component";
+        "use 2";
+        var label = props.label;
+        var value = props.value;
+        var both = m.f(function() {
+            console.log(label, m.b(value));
+        }, [
+            value
+        ]);
+        return m.j("button", [
+            [
+                function(i) {
+                    i.onclick = function() {
+                        m.g(both), void 0;
+                    };
+                }
+            ]
+        ]);
+    };
+    var count = m.a(0);
+    m.j("button", [
+        [
+            function(i) {
+                i.onclick = function() {
+                    m.c(count, m.b(count) + 1);
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Logger({
+            label: "count",
+            value: count
+        })
+    ]);
+})();
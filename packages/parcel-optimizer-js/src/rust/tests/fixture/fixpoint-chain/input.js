@@ -0,0 +1,18 @@
+(function () {
+  var m = mango;
+  var Label = function (props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /* DYNAMIC_VIEW_DEPS */[text])]);
+  };
+  var Card = function (props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    var heading = m.a("");
+    var sync = m.f(function () { m.c(heading, m.b(title)); }, /* EFFECT_DEPS */[title]);
+    return m.j("div", void 0, [Label({ text: m.a(function () { return m.b(heading) + "!"; }, /* STATE_DEPS */[heading]) })]);
+  };
+  m.o(document.body, [Card({ title: m.a("Hello") })]);
+})();
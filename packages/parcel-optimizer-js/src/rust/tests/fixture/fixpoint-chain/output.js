@@ -0,0 +1,27 @@
+(function() {
+    var m = mango;
+    var Label = function(props) {
+        "use mango_component";
+        "use 1";
+        var text = props.text;
+        return m.j("span", void 0, [
+            text
+        ]);
+    };
+    var Card = function(props) {
+        "use mango_component";
+        "use 1";
+        var title = props.title;
+        var heading = m.a("");
+        return m.j("div", void 0, [
+            Label({
+                text: m.b(heading) + "!"
+            })
+        ]);
+    };
+    m.o(document.body, [
+        Card({
+            title: "Hello"
+        })
+    ]);
+})();
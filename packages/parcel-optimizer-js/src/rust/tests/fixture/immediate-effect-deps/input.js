@@ -0,0 +1,15 @@
+(function () {
+  var m = mango;
+  var Title = function (props) {
+    "use mango_component";
+    "use 2";
+    var text = props.text;
+    var suffix = props.suffix;
+    var sync = m.f(function () { document.title = m.b(text); }, /* IMMEDIATE_EFFECT_DEPS */[text], true);
+    var syncSuffix = m.f(function () { document.title = m.b(text) + m.b(suffix); }, /* IMMEDIATE_EFFECT_DEPS */[text, suffix], true);
+    return m.j("h1", void 0, [text]);
+  };
+  var suffix = m.a("");
+  m.j("input", [/* DYNAMIC_ATTRS */[function (i) { i.oninput = function () { m.c(suffix, i.value); }; }]]);
+  m.o(document.body, [Title({ text: m.a("Home"), suffix: suffix })]);
+})();
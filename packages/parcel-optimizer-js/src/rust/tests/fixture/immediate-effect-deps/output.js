@@ -0,0 +1,36 @@
+(function() {
+    var m = mango;
+    var Title = function(props) {
+        "use mango_component";
+        "use 2";
+        var text = props.text;
+        var suffix = props.suffix;
+        (function() {
+            document.title = text;
+        })();
+        var syncSuffix = m.f(function() {
+            document.title = text + m.b(suffix);
+        }, [
+            suffix
+        ], true);
+        return m.j("h1", void 0, [
+            text
+        ]);
+    };
+    var suffix = m.a("");
+    m.j("input", [
+        [
+            function(i) {
+                i.oninput = function() {
+                    m.c(suffix, i.value);
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Title({
+            text: "Home",
+            suffix: suffix
+        })
+    ]);
+})();
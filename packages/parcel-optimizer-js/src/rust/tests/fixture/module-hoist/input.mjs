@@ -0,0 +1,17 @@
+import * as Mango from "@mango-js/runtime";
+
+export function Page(props) {
+  "use mango_component";
+  "use 1";
+  var title = props.title;
+  return Mango.j("main", void 0, [
+    Mango.i(function () { return Mango.b(title); }, /* DYNAMIC_VIEW_DEPS */[title]),
+    Mango.j("footer", [/* DYNAMIC_ATTRS */[function (i) { i.className = "legal"; }]], [Mango.j("p", void 0, ["Terms"]), Mango.j("p", void 0, ["Privacy"])]),
+  ]);
+}
+export default function () {
+  "use mango_component";
+  "use 0";
+  return Mango.j("nav", void 0, [Mango.j("a", void 0, ["Home"])]);
+}
+Mango.o(document.body, [Mango.j("header", void 0, [Mango.j("h1", void 0, ["Site"])])]);
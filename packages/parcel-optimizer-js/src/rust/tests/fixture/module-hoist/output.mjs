@@ -0,0 +1,53 @@
+import * as Mango from "@mango-js/runtime";
+var _tpl0 = [
+    function() {
+        return Mango.j("footer", [
+            [
+                function(i) {
+                    i.className = "legal";
+                }
+            ]
+        ], [
+            Mango.j("p", void 0, [
+                "Terms"
+            ]),
+            Mango.j("p", void 0, [
+                "Privacy"
+            ])
+        ]);
+    }
+];
+export function Page(props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    return Mango.j("main", void 0, [
+        Mango.i(function() {
+            return Mango.b(title);
+        }, [
+            title
+        ]),
+        Mango.p(_tpl0)
+    ]);
+}
+var _tpl1 = [
+    function() {
+        return Mango.j("nav", void 0, [
+            Mango.j("a", void 0, [
+                "Home"
+            ])
+        ]);
+    }
+];
+export default function() {
+    "use mango_component";
+    "use 0";
+    return Mango.p(_tpl1);
+}
+Mango.o(document.body, [
+    Mango.j("header", void 0, [
+        Mango.j("h1", void 0, [
+            "Site"
+        ])
+    ])
+]);
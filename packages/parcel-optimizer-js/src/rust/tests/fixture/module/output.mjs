@@ -0,0 +1,76 @@
+import * as Mango from "@mango-js/runtime";
+import { Header } from "./header";
+function Label(props) {
+    "use mango_component";
+    "use 1";
+    var text = props.text;
+    return Mango.j("span", void 0, [
+        text
+    ]);
+}
+var Badge = function(props) {
+    "use mango_component";
+    "use 1";
+    var tone = props.tone;
+    return Mango.j("i", void 0, [
+        Mango.i(function() {
+            return Mango.b(tone);
+        }, [
+            tone
+        ])
+    ]);
+};
+export const Card = function(props) {
+    "use mango_component";
+    "use 1";
+    var title = props.title;
+    return Mango.j("h2", void 0, [
+        Mango.i(function() {
+            return Mango.b(title);
+        }, [
+            title
+        ])
+    ]);
+};
+export const theme = Mango.a("light");
+var count = Mango.a(0);
+export default function Page(props) {
+    "use mango_component";
+    "use 1";
+    var heading = props.heading;
+    Mango.j("button", [
+        [
+            function(i) {
+                i.onclick = function() {
+                    Mango.c(count, Mango.b(count) + 1);
+                };
+            }
+        ]
+    ]);
+    return Mango.j("main", void 0, [
+        Header({
+            title: Mango.a("Hi")
+        }),
+        Mango.i(function() {
+            return Mango.b(heading);
+        }, [
+            heading
+        ]),
+        Label({
+            text: "static"
+        }),
+        Label({
+            text: "again"
+        }),
+        Badge({
+            tone: theme
+        }),
+        Badge({
+            tone: Mango.a("red")
+        }),
+        Card({
+            title: Mango.a("Card")
+        })
+    ]);
+}
+export { Badge };
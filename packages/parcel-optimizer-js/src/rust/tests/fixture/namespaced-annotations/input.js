@@ -0,0 +1,14 @@
+(function () {
+  var m = mango;
+  var Badge = function (props) {
+    "use mango_component";
+    "use 2";
+    var text = props.text;
+    var tone = props.tone;
+    m.f(function () { console.log(m.b(text), m.b(tone)); }, /* keep me */ /*@mango:effect-deps*/[text, tone]);
+    return m.j("span", void 0, [m.i(function () { return m.b(text); }, /*#__PURE__*/ /*  DYNAMIC_VIEW_DEPS*/[text])]);
+  };
+  var count = m.a(0);
+  m.j("button", [/* @mango:dynamic-attrs */[function (i) { i.onclick = function () { m.c(count, m.b(count) + 1); }; }]]);
+  m.o(document.body, [Badge({ text: m.a("new"), tone: m.a(function () { return m.b(count) + 1; }, /* @mango:state-deps */[count]) })]);
+})();
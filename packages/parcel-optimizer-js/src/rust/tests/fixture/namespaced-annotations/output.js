@@ -0,0 +1,37 @@
+(function() {
+    var m = mango;
+    var Badge = function(props) {
+        "use mango_component";
+        "use 2";
+        var text = props.text;
+        var tone = props.tone;
+        m.f(function() {
+            console.log(text, m.b(tone));
+        }, /* keep me */ [
+            tone
+        ]);
+        return m.j("span", void 0, [
+            text
+        ]);
+    };
+    var count = m.a(0);
+    m.j("button", [
+        [
+            function(i) {
+                i.onclick = function() {
+                    m.c(count, m.b(count) + 1);
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Badge({
+            text: "new",
+            tone: m.a(function() {
+                return m.b(count) + 1;
+            }, [
+                count
+            ])
+        })
+    ]);
+})();
@@ -0,0 +1,11 @@
+(function () {
+  var m = mango;
+  var Avatar = function (props) {
+    "use mango_component";
+    "use 2";
+    var src = props.src;
+    var alt = props.alt;
+    return m.j("img", [/* DYNAMIC_ATTRS */[function (i) { i.src = m.b(src); }, src], /* DYNAMIC_ATTRS */[function (i) { i.alt = m.b(alt) || ""; }, alt]]);
+  };
+  m.o(document.body, [Avatar({ src: m.a("a.png") }), Avatar({ src: m.a("b.png") })]);
+})();
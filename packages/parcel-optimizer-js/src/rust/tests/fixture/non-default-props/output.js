@@ -0,0 +1,29 @@
+(function() {
+    var m = mango;
+    var Avatar = function(props) {
+        "use mango_component";
+        "use 2";
+        var src = props.src;
+        var alt = undefined;
+        return m.j("img", [
+            [
+                function(i) {
+                    i.src = src;
+                }
+            ],
+            [
+                function(i) {
+                    i.alt = alt || "";
+                }
+            ]
+        ]);
+    };
+    m.o(document.body, [
+        Avatar({
+            src: "a.png"
+        }),
+        Avatar({
+            src: "b.png"
+        })
+    ]);
+})();
@@ -0,0 +1,48 @@
+(function() {
+    var m = mango;
+    var Price = function(props) {
+        "use mango_component";
+        "use 2";
+        var amount = props.amount;
+        var currency = props.currency;
+        return m.j("span", void 0, [
+            m.i(function() {
+                return m.b(amount) + " " + currency;
+            }, [
+                amount
+            ])
+        ]);
+    };
+    var Cart = function(props) {
+        "use mango_component";
+        "use 2";
+        var total = props.total;
+        var currency = props.currency;
+        return m.j("div", void 0, [
+            Price({
+                amount: m.a(function() {
+                    return m.b(total) * 100;
+                }, [
+                    total
+                ]),
+                currency: currency.toUpperCase()
+            })
+        ]);
+    };
+    var total = m.a(0);
+    m.j("button", [
+        [
+            function(i) {
+                i.onclick = function() {
+                    m.c(total, m.b(total) + 1);
+                };
+            }
+        ]
+    ]);
+    m.o(document.body, [
+        Cart({
+            total: total,
+            currency: "eur"
+        })
+    ]);
+})();
@@ -0,0 +1,11 @@
+(function () {
+  var m = mango;
+  var Tag = function (props) {
+    "use mango_component";
+    "use 2";
+    var label = props.label;
+    var tone = props.tone;
+    return m.j("i", void 0, [m.i(function () { return m.b(label) + m.b(tone); }, /* DYNAMIC_VIEW_DEPS */[label, tone])]);
+  };
+  m.o(document.body, [Tag({ "label": m.a("new"), "tone": m.a("info") })]);
+})();
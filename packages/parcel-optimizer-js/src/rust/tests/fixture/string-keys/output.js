@@ -0,0 +1,18 @@
+(function() {
+    var m = mango;
+    var Tag = function(props) {
+        "use mango_component";
+        "use 2";
+        var label = props.label;
+        var tone = props.tone;
+        return m.j("i", void 0, [
+            label + tone
+        ]);
+    };
+    m.o(document.body, [
+        Tag({
+            "label": "new",
+            "tone": "info"
+        })
+    ]);
+})();
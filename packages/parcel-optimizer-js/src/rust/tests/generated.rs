@@ -0,0 +1,596 @@
+//! Generates random programs shaped like the output of the JSX plugin, then checks that optimizing them
+//! keeps every invariant and is idempotent. Failing programs are shrunk to a minimal reproduction.
+//! Set `MANGO_GEN_SEED` to replay a single seed and `MANGO_GEN_CASES` to change the number of cases.
+
+use std::env;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use mango_optimizer_js::{optimize, Options};
+use swc_common::comments::SingleThreadedComments;
+use swc_common::errors::{Handler, HANDLER};
+use swc_common::{FileName, Mark, SourceMap, GLOBALS};
+use swc_ecma_ast::{EsVersion, Program};
+use swc_ecma_codegen::{text_writer::JsWriter, Emitter};
+use swc_ecma_parser::{parse_file_as_script, Syntax};
+use swc_ecma_transforms::resolver;
+
+const DEFAULT_CASES: u64 = 300;
+
+struct Rng(u64);
+
+impl Rng {
+  fn new(seed: u64) -> Self {
+    Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+  }
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 >> 12;
+    self.0 ^= self.0 << 25;
+    self.0 ^= self.0 >> 27;
+    self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+  fn below(&mut self, n: usize) -> usize {
+    (self.next() % n as u64) as usize
+  }
+  fn chance(&mut self, percent: u64) -> bool {
+    self.next() % 100 < percent
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Ref {
+  Prop(usize),
+  Local(usize),
+  Global(usize),
+}
+
+#[derive(Clone, Debug)]
+enum Arg {
+  Literal(usize),
+  Bound(Ref),
+  Derived(Vec<Ref>),
+}
+
+#[derive(Clone, Debug)]
+struct Call {
+  component: usize,
+  args: Vec<(usize, Arg)>,
+}
+
+#[derive(Clone, Debug)]
+enum Local {
+  State(usize),
+  Derived(Vec<Ref>),
+  Effect { deps: Vec<Ref>, immediate: bool },
+}
+
+#[derive(Clone, Debug)]
+enum Child {
+  Bound(Ref),
+  View(Vec<Ref>),
+  Attrs(Vec<Ref>),
+  Call(Call),
+}
+
+#[derive(Clone, Debug)]
+struct Component {
+  defaults: Vec<Option<usize>>,
+  locals: Vec<Local>,
+  children: Vec<Child>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Model {
+  globals: Vec<bool>,
+  components: Vec<Component>,
+  mounts: Vec<Call>,
+}
+
+fn gen_ref(rng: &mut Rng, props: usize, locals: usize, globals: usize) -> Ref {
+  match rng.below(3) {
+    0 if props > 0 => Ref::Prop(rng.below(props)),
+    1 if locals > 0 => Ref::Local(rng.below(locals)),
+    _ => Ref::Global(rng.below(globals.max(1))),
+  }
+}
+
+fn gen_refs(rng: &mut Rng, props: usize, locals: usize, globals: usize) -> Vec<Ref> {
+  (0..1 + rng.below(3))
+    .map(|_| gen_ref(rng, props, locals, globals))
+    .collect()
+}
+
+fn gen_call(rng: &mut Rng, components: &[Component], props: usize, locals: usize, globals: usize) -> Call {
+  let component = rng.below(components.len());
+  let declared = components[component].defaults.len();
+  let mut args = vec![];
+  for prop in 0..declared {
+    if rng.chance(75) {
+      args.push((
+        prop,
+        match rng.below(3) {
+          0 => Arg::Literal(rng.below(10)),
+          1 => Arg::Bound(gen_ref(rng, props, locals, globals)),
+          _ => Arg::Derived(gen_refs(rng, props, locals, globals)),
+        },
+      ));
+    }
+  }
+  Call { component, args }
+}
+
+fn gen_model(rng: &mut Rng) -> Model {
+  let globals: Vec<bool> = (0..1 + rng.below(3)).map(|_| rng.chance(50)).collect();
+  let mut components: Vec<Component> = vec![];
+  for _ in 0..1 + rng.below(4) {
+    let defaults: Vec<Option<usize>> = (0..rng.below(4))
+      .map(|_| rng.chance(30).then(|| rng.below(10)))
+      .collect();
+    let props = defaults.len();
+    let mut locals = vec![];
+    for idx in 0..rng.below(4) {
+      locals.push(match rng.below(3) {
+        0 => Local::State(rng.below(10)),
+        1 => Local::Derived(gen_refs(rng, props, idx, globals.len())),
+        _ => Local::Effect {
+          deps: gen_refs(rng, props, idx, globals.len()),
+          immediate: rng.chance(50),
+        },
+      });
+    }
+    let mut children = vec![];
+    for _ in 0..1 + rng.below(4) {
+      children.push(match rng.below(4) {
+        0 => Child::Bound(gen_ref(rng, props, locals.len(), globals.len())),
+        1 => Child::View(gen_refs(rng, props, locals.len(), globals.len())),
+        2 => Child::Attrs(gen_refs(rng, props, locals.len(), globals.len())),
+        _ if !components.is_empty() => Child::Call(gen_call(rng, &components, props, locals.len(), globals.len())),
+        _ => Child::View(gen_refs(rng, props, locals.len(), globals.len())),
+      });
+    }
+    components.push(Component {
+      defaults,
+      locals,
+      children,
+    });
+  }
+  let mounts = (0..1 + rng.below(3))
+    .map(|_| gen_call(rng, &components, 0, 0, globals.len()))
+    .collect();
+  Model {
+    globals,
+    components,
+    mounts,
+  }
+}
+
+// Refs that point at nothing, e.g. after shrinking, are left out when rendering.
+struct Scope<'a> {
+  defaults: &'a [Option<usize>],
+  locals: &'a [Local],
+  globals: usize,
+}
+
+impl Scope<'_> {
+  fn is_state(&self, r: Ref) -> bool {
+    match r {
+      Ref::Prop(idx) => idx < self.defaults.len(),
+      Ref::Local(idx) => matches!(self.locals.get(idx), Some(Local::State(_) | Local::Derived(_))),
+      Ref::Global(idx) => idx < self.globals,
+    }
+  }
+  fn is_default(&self, r: Ref) -> bool {
+    matches!(r, Ref::Prop(idx) if self.defaults[idx].is_some())
+  }
+  fn name(r: Ref) -> String {
+    match r {
+      Ref::Prop(idx) => format!("p{}", idx),
+      Ref::Local(idx) => format!("s{}", idx),
+      Ref::Global(idx) => format!("g{}", idx),
+    }
+  }
+  fn value(&self, refs: &[Ref]) -> String {
+    let values: Vec<String> = refs
+      .iter()
+      .filter(|r| self.is_state(**r))
+      .map(|r| match r {
+        Ref::Prop(idx) if self.is_default(*r) => format!("(_u{} ? m.b(p{}) : p{})", idx, idx, idx),
+        _ => format!("m.b({})", Self::name(*r)),
+      })
+      .collect();
+    if values.is_empty() {
+      "0".into()
+    } else {
+      values.join(" + ")
+    }
+  }
+  fn deps(&self, refs: &[Ref]) -> String {
+    let deps: Vec<String> = refs
+      .iter()
+      .filter(|r| self.is_state(**r))
+      .map(|r| match r {
+        Ref::Prop(idx) if self.is_default(*r) => format!("_u{} ? p{} : undefined", idx, idx),
+        _ => Self::name(*r),
+      })
+      .collect();
+    deps.join(", ")
+  }
+  fn bound(&self, r: Ref) -> Option<String> {
+    (self.is_state(r) && !self.is_default(r)).then(|| Self::name(r))
+  }
+  fn call(&self, call: &Call, components: &[Component]) -> Option<String> {
+    let component = components.get(call.component)?;
+    let args: Vec<String> = call
+      .args
+      .iter()
+      .filter(|(prop, _)| *prop < component.defaults.len())
+      .filter_map(|(prop, arg)| {
+        let value = match arg {
+          Arg::Literal(value) => format!("m.a({})", value),
+          Arg::Bound(r) => self.bound(*r)?,
+          Arg::Derived(refs) => format!(
+            "m.a(function () {{ return {}; }}, /* STATE_DEPS */[{}])",
+            self.value(refs),
+            self.deps(refs)
+          ),
+        };
+        Some(format!("p{}: {}", prop, value))
+      })
+      .collect();
+    Some(format!("C{}({{ {} }})", call.component, args.join(", ")))
+  }
+}
+
+fn render(model: &Model) -> String {
+  let mut code = String::from("(function () {\n  var m = mango;\n");
+  for (idx, mutated) in model.globals.iter().enumerate() {
+    code.push_str(&format!("  var g{} = m.a({});\n", idx, idx));
+    if *mutated {
+      code.push_str(&format!(
+        "  m.j(\"button\", [/* DYNAMIC_ATTRS */[function (i) {{ i.onclick = function () {{ m.c(g{}, m.b(g{}) + 1); }}; }}]]);\n",
+        idx, idx
+      ));
+    }
+  }
+  for (idx, component) in model.components.iter().enumerate() {
+    let mut preamble = vec![];
+    for (prop, default) in component.defaults.iter().enumerate() {
+      match default {
+        Some(value) => {
+          preamble.push(format!("var p{} = props.p{} || {};", prop, prop, value));
+          preamble.push(format!("var _u{} = props.p{} !== undefined;", prop, prop));
+        }
+        None => preamble.push(format!("var p{} = props.p{};", prop, prop)),
+      }
+    }
+    code.push_str(&format!(
+      "  var C{} = function (props) {{\n    \"use mango_component\";\n    \"use {}\";\n",
+      idx,
+      preamble.len()
+    ));
+    for line in preamble {
+      code.push_str(&format!("    {}\n", line));
+    }
+    for (local, init) in component.locals.iter().enumerate() {
+      let scope = Scope {
+        defaults: &component.defaults,
+        locals: &component.locals[..local],
+        globals: model.globals.len(),
+      };
+      let init = match init {
+        Local::State(value) => format!("m.a({})", value),
+        Local::Derived(refs) => format!(
+          "m.a(function () {{ return {}; }}, /* STATE_DEPS */[{}])",
+          scope.value(refs),
+          scope.deps(refs)
+        ),
+        Local::Effect { deps, immediate: false } => format!(
+          "m.f(function () {{ console.log({}); }}, /* EFFECT_DEPS */[{}])",
+          scope.value(deps),
+          scope.deps(deps)
+        ),
+        Local::Effect { deps, immediate: true } => format!(
+          "m.f(function () {{ console.log({}); }}, /* IMMEDIATE_EFFECT_DEPS */[{}], true)",
+          scope.value(deps),
+          scope.deps(deps)
+        ),
+      };
+      code.push_str(&format!("    var s{} = {};\n", local, init));
+    }
+    let scope = Scope {
+      defaults: &component.defaults,
+      locals: &component.locals,
+      globals: model.globals.len(),
+    };
+    let children: Vec<String> = component
+      .children
+      .iter()
+      .filter_map(|child| match child {
+        Child::Bound(r) => scope.bound(*r),
+        Child::View(refs) => Some(format!(
+          "m.i(function () {{ return {}; }}, /* DYNAMIC_VIEW_DEPS */[{}])",
+          scope.value(refs),
+          scope.deps(refs)
+        )),
+        Child::Attrs(refs) => Some(format!(
+          "m.j(\"span\", [/* DYNAMIC_ATTRS */[function (i) {{ i.title = {}; }}, {}]])",
+          scope.value(refs),
+          scope.deps(refs)
+        )),
+        Child::Call(call) if call.component < idx => scope.call(call, &model.components),
+        Child::Call(_) => None,
+      })
+      .collect();
+    code.push_str(&format!(
+      "    return m.j(\"div\", void 0, [{}]);\n  }};\n",
+      children.join(", ")
+    ));
+  }
+  let scope = Scope {
+    defaults: &[],
+    locals: &[],
+    globals: model.globals.len(),
+  };
+  let mounts: Vec<String> = model
+    .mounts
+    .iter()
+    .filter_map(|call| scope.call(call, &model.components))
+    .collect();
+  code.push_str(&format!("  m.o(document.body, [{}]);\n}})();\n", mounts.join(", ")));
+  code
+}
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.lock().unwrap().write(buf)
+  }
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+fn optimize_source(code: &str) -> Result<String, String> {
+  let cm: Arc<SourceMap> = Default::default();
+  let fm = cm.new_source_file(FileName::Anon.into(), code.to_string());
+  let comments = SingleThreadedComments::default();
+  let script = parse_file_as_script(
+    &fm,
+    Syntax::Es(Default::default()),
+    EsVersion::latest(),
+    Some(&comments),
+    &mut vec![],
+  )
+  .map_err(|err| format!("failed to parse: {:?}", err))?;
+  let buffer = Buffer::default();
+  let handler = Handler::with_emitter_writer(Box::new(buffer.clone()), Some(cm.clone()));
+  let mut src = vec![];
+  GLOBALS.set(&Default::default(), || {
+    HANDLER.set(&handler, || {
+      let mut program = Program::Script(script).apply(&mut resolver(Mark::new(), Mark::new(), false));
+      optimize(
+        &mut program,
+        &comments,
+        &Options {
+          verify: true,
+          keep_annotations: true,
+          ..Default::default()
+        },
+      );
+      let mut emitter = Emitter {
+        cfg: Default::default(),
+        comments: Some(&comments),
+        cm: cm.clone(),
+        wr: Box::new(JsWriter::new(cm.clone(), "\n", &mut src, None)),
+      };
+      emitter.emit_program(&program).unwrap();
+    })
+  });
+  if handler.has_errors() {
+    return Err(String::from_utf8_lossy(&buffer.0.lock().unwrap()).into_owned());
+  }
+  Ok(String::from_utf8(src).unwrap())
+}
+
+fn check(model: &Model) -> Result<(), String> {
+  let once = optimize_source(&render(model))?;
+  let twice = optimize_source(&once).map_err(|err| format!("second pass: {}", err))?;
+  if once != twice {
+    return Err(format!(
+      "optimizing is not idempotent\n--- once ---\n{}\n--- twice ---\n{}",
+      once, twice
+    ));
+  }
+  Ok(())
+}
+
+fn remove_each<T: Clone>(items: &[T]) -> impl Iterator<Item = Vec<T>> + '_ {
+  (0..items.len()).map(move |idx| {
+    let mut items = items.to_vec();
+    items.remove(idx);
+    items
+  })
+}
+
+fn shrink_refs(refs: &[Ref]) -> Vec<Vec<Ref>> {
+  remove_each(refs).collect()
+}
+
+fn shrink_call(call: &Call) -> Vec<Call> {
+  let mut candidates: Vec<Call> = remove_each(&call.args)
+    .map(|args| Call { args, ..call.clone() })
+    .collect();
+  for (idx, (prop, arg)) in call.args.iter().enumerate() {
+    let simpler = match arg {
+      Arg::Literal(_) => vec![],
+      Arg::Bound(_) => vec![Arg::Literal(0)],
+      Arg::Derived(refs) => shrink_refs(refs)
+        .into_iter()
+        .map(Arg::Derived)
+        .chain([Arg::Literal(0)])
+        .collect(),
+    };
+    for arg in simpler {
+      let mut call = call.clone();
+      call.args[idx] = (*prop, arg);
+      candidates.push(call);
+    }
+  }
+  candidates
+}
+
+fn shrink_component(component: &Component) -> Vec<Component> {
+  let mut candidates = vec![];
+  for defaults in remove_each(&component.defaults) {
+    candidates.push(Component {
+      defaults,
+      ..component.clone()
+    });
+  }
+  for (idx, default) in component.defaults.iter().enumerate() {
+    if default.is_some() {
+      let mut component = component.clone();
+      component.defaults[idx] = None;
+      candidates.push(component);
+    }
+  }
+  for locals in remove_each(&component.locals) {
+    candidates.push(Component {
+      locals,
+      ..component.clone()
+    });
+  }
+  for (idx, local) in component.locals.iter().enumerate() {
+    let simpler = match local {
+      Local::State(_) => vec![],
+      Local::Derived(refs) => shrink_refs(refs)
+        .into_iter()
+        .map(Local::Derived)
+        .chain([Local::State(0)])
+        .collect(),
+      Local::Effect { deps, immediate } => shrink_refs(deps)
+        .into_iter()
+        .map(|deps| Local::Effect {
+          deps,
+          immediate: *immediate,
+        })
+        .chain(immediate.then(|| Local::Effect {
+          deps: deps.clone(),
+          immediate: false,
+        }))
+        .collect(),
+    };
+    for local in simpler {
+      let mut component = component.clone();
+      component.locals[idx] = local;
+      candidates.push(component);
+    }
+  }
+  for children in remove_each(&component.children) {
+    candidates.push(Component {
+      children,
+      ..component.clone()
+    });
+  }
+  for (idx, child) in component.children.iter().enumerate() {
+    let simpler: Vec<Child> = match child {
+      Child::Bound(_) => vec![],
+      Child::View(refs) => shrink_refs(refs).into_iter().map(Child::View).collect(),
+      Child::Attrs(refs) => shrink_refs(refs).into_iter().map(Child::Attrs).collect(),
+      Child::Call(call) => shrink_call(call).into_iter().map(Child::Call).collect(),
+    };
+    for child in simpler {
+      let mut component = component.clone();
+      component.children[idx] = child;
+      candidates.push(component);
+    }
+  }
+  candidates
+}
+
+fn shrink_candidates(model: &Model) -> Vec<Model> {
+  let mut candidates = vec![];
+  for components in remove_each(&model.components) {
+    candidates.push(Model {
+      components,
+      ..model.clone()
+    });
+  }
+  for mounts in remove_each(&model.mounts) {
+    candidates.push(Model {
+      mounts,
+      ..model.clone()
+    });
+  }
+  for globals in remove_each(&model.globals) {
+    candidates.push(Model {
+      globals,
+      ..model.clone()
+    });
+  }
+  for (idx, mutated) in model.globals.iter().enumerate() {
+    if *mutated {
+      let mut model = model.clone();
+      model.globals[idx] = false;
+      candidates.push(model);
+    }
+  }
+  for (idx, component) in model.components.iter().enumerate() {
+    for component in shrink_component(component) {
+      let mut model = model.clone();
+      model.components[idx] = component;
+      candidates.push(model);
+    }
+  }
+  for (idx, call) in model.mounts.iter().enumerate() {
+    for call in shrink_call(call) {
+      let mut model = model.clone();
+      model.mounts[idx] = call;
+      candidates.push(model);
+    }
+  }
+  candidates
+}
+
+fn shrink(mut model: Model, mut error: String) -> (Model, String) {
+  'outer: loop {
+    for candidate in shrink_candidates(&model) {
+      if let Err(candidate_error) = check(&candidate) {
+        model = candidate;
+        error = candidate_error;
+        continue 'outer;
+      }
+    }
+    return (model, error);
+  }
+}
+
+#[test]
+fn test_generated_programs() {
+  let seeds = match env::var("MANGO_GEN_SEED") {
+    Ok(seed) => {
+      let seed = seed.parse().expect("`MANGO_GEN_SEED` must be a number");
+      seed..seed + 1
+    }
+    Err(_) => {
+      let cases = env::var("MANGO_GEN_CASES").map_or(DEFAULT_CASES, |cases| {
+        cases.parse().expect("`MANGO_GEN_CASES` must be a number")
+      });
+      0..cases
+    }
+  };
+  for seed in seeds {
+    let model = gen_model(&mut Rng::new(seed));
+    if let Err(error) = check(&model) {
+      let (model, error) = shrink(model, error);
+      panic!(
+        "seed {} fails, minimal reproduction:\n{}\n{}",
+        seed,
+        render(&model),
+        error
+      );
+    }
+  }
+}
@@ -91,6 +91,11 @@
  * @typedef {Comment & { $d: MangoNode }} TeleporterNode
  */
 
+/**
+ * Static node built on first use and deeply cloned afterwards.
+ * @typedef {[() => MangoNode, MangoNode?]} Template
+ */
+
 /**
  * A reference to track an effect and includes its dependencies.
  * @typedef {State[]} Effect
@@ -372,6 +377,35 @@ function createElement(tag, props, children, ns) {
   return node;
 }
 
+/**
+ * Creates a deep copy of a static node built by a template.
+ * @param {Template} template - Template building the static node.
+ * @returns {MangoNode} Node of the newly cloned element.
+ */
+function cloneTemplate(template) {
+  if (!template[1]) template[1] = template[0]();
+  return /** @type {MangoNode} */ (template[1].cloneNode(true));
+}
+
+/**
+ * Creates a static element from its pre-rendered HTML markup.
+ * @param {string} html - HTML markup of the element and its wrappers.
+ * @param {number} [depth] - Number of wrappers enclosing the element.
+ * @returns {MangoNode} Node of the newly created element.
+ */
+function createStaticElement(html, depth) {
+  var fragment = document.createDocumentFragment();
+  if (fragment.createElement) {
+    var html5Tags = "abbr article aside audio bdi canvas data datalist details dialog figcaption figure footer header hgroup main mark meter nav output picture progress section summary template time video".split(" ");
+    for (var i = 0; i < html5Tags.length; i++) fragment.createElement(html5Tags[i]);
+  }
+  /** @type {Node} */
+  var node = fragment.appendChild(document.createElement("div"));
+  /** @type {HTMLElement} */ (node).innerHTML = html;
+  for (var j = 0; j <= (depth || 0); j++) node = /** @type {Node} */ (node.firstChild);
+  return /** @type {MangoNode} */ (/** @type {Node} */ (node.parentNode).removeChild(node));
+}
+
 /**
  * Create a new teleporter node used as a pointer to another node.
  * @param {MangoNode} dest - Node to be teleported to.
@@ -754,7 +788,9 @@ window.mango = {
   l: appendPropsToElement,
   m: createLazyComponent,
   n: createServerFunctionInvoker,
-  o: appendChildrenToElement
+  o: appendChildrenToElement,
+  p: cloneTemplate,
+  q: createStaticElement
 }
 
 })();
@@ -95,6 +95,19 @@ for (const pkg of packages) {
     }
   }
 
+  if (pkg === "parcel-optimizer-js") {
+    const rustPath = path.join(srcPath, "rust");
+    for (const alias of ["build-release", "build-pipeline"]) {
+      const res = spawnSync("cargo", [alias], { cwd: rustPath, shell: true, stdio: "inherit" });
+      if (res.status !== 0) {
+        process.exit(res.status);
+      }
+    }
+    for (const wasm of ["mango_optimizer_js.wasm", "mango_optimizer_pipeline.wasm"]) {
+      await asyncFs.copyFile(path.join(rustPath, "target", "wasm32-wasip1", "release", wasm), path.join(srcPath, wasm));
+    }
+  }
+
   await iterateDir(pkgPath, async (filePath) => {
     const srcFilePath = filePath;
     const distFilePath = filePath.replace(pkgPath, buildPath);